@@ -0,0 +1,213 @@
+use anchor_lang::prelude::*;
+
+#[event]
+pub struct PoolInitialized {
+    pub pool: Pubkey,
+    pub token_mint_0: Pubkey,
+    pub token_mint_1: Pubkey,
+    pub token_vault_0: Pubkey,
+    pub token_vault_1: Pubkey,
+    pub tick_spacing: i32,
+    pub sqrt_price_x96: u128,
+    pub tick: i32,
+}
+
+#[event]
+pub struct PauseFlagsUpdated {
+    pub pool: Pubkey,
+    pub authority: Pubkey,
+    pub pause_flags: u8,
+}
+
+#[event]
+pub struct DynamicFeeUpdated {
+    pub pool: Pubkey,
+    pub authority: Pubkey,
+    pub enabled: bool,
+    pub min_fee_rate: u32,
+    pub max_fee_rate: u32,
+}
+
+#[event]
+pub struct PositionOpened {
+    pub pool: Pubkey,
+    pub position: Pubkey,
+    pub owner: Pubkey,
+    pub tick_lower: i32,
+    pub tick_upper: i32,
+    pub liquidity: u128,
+    pub amount_0: u64,
+    pub amount_1: u64,
+}
+
+#[event]
+pub struct LiquidityIncreased {
+    pub pool: Pubkey,
+    pub position: Pubkey,
+    pub owner: Pubkey,
+    pub tick_lower: i32,
+    pub tick_upper: i32,
+    pub liquidity: u128,
+    pub amount_0: u64,
+    pub amount_1: u64,
+}
+
+#[event]
+pub struct LiquidityDecreased {
+    pub pool: Pubkey,
+    pub position: Pubkey,
+    pub owner: Pubkey,
+    pub tick_lower: i32,
+    pub tick_upper: i32,
+    pub liquidity: u128,
+    pub amount_0: u64,
+    pub amount_1: u64,
+}
+
+#[event]
+pub struct Flash {
+    pub pool: Pubkey,
+    pub sender: Pubkey,
+    pub amount_0: u64,
+    pub amount_1: u64,
+    pub paid_0: u64,
+    pub paid_1: u64,
+}
+
+#[event]
+pub struct Swap {
+    pub pool: Pubkey,
+    pub sender: Pubkey,
+    pub swap_token_0_for_1: bool,
+    pub amount_in: u64,
+    pub amount_out: u64,
+    pub sqrt_price_x96_before: u128,
+    pub sqrt_price_x96_after: u128,
+    pub tick_before: i32,
+    pub tick_after: i32,
+    pub liquidity: u128,
+}
+
+#[event]
+pub struct LimitOrderPlaced {
+    pub pool: Pubkey,
+    pub limit_order: Pubkey,
+    pub owner: Pubkey,
+    pub tick: i32,
+    pub zero_for_one: bool,
+    pub epoch: u64,
+    pub amount_in: u64,
+}
+
+#[event]
+pub struct LimitOrdersFilled {
+    pub pool: Pubkey,
+    pub tick: i32,
+    pub epoch: u64,
+    pub zero_for_one: bool,
+    pub amount_in: u64,
+    pub amount_out: u64,
+}
+
+#[event]
+pub struct LimitOrderClaimed {
+    pub pool: Pubkey,
+    pub limit_order: Pubkey,
+    pub owner: Pubkey,
+    pub tick: i32,
+    pub amount_in: u64,
+    pub amount_out: u64,
+}
+
+#[event]
+pub struct LimitOrderCancelled {
+    pub pool: Pubkey,
+    pub limit_order: Pubkey,
+    pub owner: Pubkey,
+    pub tick: i32,
+    pub amount_in: u64,
+}
+
+#[event]
+pub struct FeesCollected {
+    pub pool: Pubkey,
+    pub position: Pubkey,
+    pub owner: Pubkey,
+    pub amount_0: u64,
+    pub amount_1: u64,
+}
+
+#[event]
+pub struct PositionLocked {
+    pub pool: Pubkey,
+    pub position: Pubkey,
+    pub owner: Pubkey,
+    pub lock_until: i64,
+}
+
+#[event]
+pub struct PositionOperatorUpdated {
+    pub pool: Pubkey,
+    pub position: Pubkey,
+    pub owner: Pubkey,
+    pub operator: Pubkey,
+}
+
+#[event]
+pub struct PositionKeeperUpdated {
+    pub pool: Pubkey,
+    pub position: Pubkey,
+    pub owner: Pubkey,
+    pub keeper: Pubkey,
+}
+
+#[event]
+pub struct PositionTransferred {
+    pub pool: Pubkey,
+    pub old_position: Pubkey,
+    pub new_position: Pubkey,
+    pub old_owner: Pubkey,
+    pub new_owner: Pubkey,
+    pub liquidity: u128,
+}
+
+#[event]
+pub struct PositionSplit {
+    pub pool: Pubkey,
+    pub position: Pubkey,
+    pub new_position: Pubkey,
+    pub new_owner: Pubkey,
+    pub liquidity: u128,
+}
+
+#[event]
+pub struct PositionsMerged {
+    pub pool: Pubkey,
+    pub source_position: Pubkey,
+    pub destination_position: Pubkey,
+    pub liquidity: u128,
+}
+
+#[event]
+pub struct BundledLiquidityChanged {
+    pub pool: Pubkey,
+    pub position_bundle: Pubkey,
+    pub slot: u8,
+    pub tick_lower: i32,
+    pub tick_upper: i32,
+    pub liquidity_delta: i128,
+    pub amount_0: u64,
+    pub amount_1: u64,
+}
+
+#[event]
+pub struct PositionCompounded {
+    pub pool: Pubkey,
+    pub position: Pubkey,
+    pub keeper: Pubkey,
+    pub fees_0: u64,
+    pub fees_1: u64,
+    pub bounty_0: u64,
+    pub bounty_1: u64,
+    pub liquidity: u128,
+}
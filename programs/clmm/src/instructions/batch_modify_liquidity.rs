@@ -0,0 +1,284 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::{Mint, TokenAccount, TokenInterface};
+use crate::events;
+use crate::states::*;
+use crate::utils::ErrorCode;
+use crate::utils::math::*;
+use crate::utils::token::*;
+use crate::utils::init_pda_if_needed;
+
+pub const MAX_BATCH_ENTRIES: usize = 8;
+
+/// Remaining accounts per entry: `[lower_tick_array, upper_tick_array, position]`.
+/// Missing tick arrays and positions are created for positive deltas.
+/// Transfer-hook extra accounts, if any, follow the last entry.
+pub const ACCOUNTS_PER_ENTRY: usize = 3;
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct LiquidityEntry {
+    pub lower_tick: i32,
+    pub upper_tick: i32,
+    pub liquidity_delta: i128,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct BatchModifyLiquidity<'info> {
+    #[account(
+        mut,
+        has_one = token_mint_0,
+        has_one = token_mint_1,
+    )]
+    pub pool: Account<'info, Pool>,
+
+    #[account(
+        mut,
+        constraint = user_token_0.mint == pool.token_mint_0 @ ErrorCode::InvalidTokenMint,
+        constraint = user_token_0.owner == owner.key() @ ErrorCode::InvalidTokenAccountOwner,
+    )]
+    pub user_token_0: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        constraint = user_token_1.mint == pool.token_mint_1 @ ErrorCode::InvalidTokenMint,
+        constraint = user_token_1.owner == owner.key() @ ErrorCode::InvalidTokenAccountOwner,
+    )]
+    pub user_token_1: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        address = pool.token_vault_0 @ ErrorCode::InvalidPoolVault,
+    )]
+    pub pool_token_0: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        address = pool.token_vault_1 @ ErrorCode::InvalidPoolVault,
+    )]
+    pub pool_token_1: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    pub token_mint_0: InterfaceAccount<'info, Mint>,
+    pub token_mint_1: InterfaceAccount<'info, Mint>,
+    pub system_program: Program<'info, System>,
+    pub token_program_0: Interface<'info, TokenInterface>,
+    pub token_program_1: Interface<'info, TokenInterface>,
+}
+
+/// Applies every entry to the owner's positions, then settles the net amount
+/// of each token with a single transfer. Returns the net amounts paid in by
+/// the owner; negative values were paid out.
+pub fn batch_modify_liquidity<'info>(
+    ctx: Context<'_, '_, 'info, 'info, BatchModifyLiquidity<'info>>,
+    entries: Vec<LiquidityEntry>,
+) -> Result<(i128, i128)> {
+    require!(
+        !entries.is_empty()
+            && entries.len() <= MAX_BATCH_ENTRIES
+            && ctx.remaining_accounts.len() >= entries.len() * ACCOUNTS_PER_ENTRY,
+        ErrorCode::InvalidBatch
+    );
+
+    let pool = &mut ctx.accounts.pool;
+    if entries.iter().any(|entry| entry.liquidity_delta > 0) {
+        pool.require_not_paused(PAUSE_DEPOSIT)?;
+    }
+    if entries.iter().any(|entry| entry.liquidity_delta < 0) {
+        pool.require_not_paused(PAUSE_WITHDRAW)?;
+    }
+
+    let pool_key = pool.key();
+    let owner = ctx.accounts.owner.key();
+    let unix_timestamp = Clock::get()?.unix_timestamp;
+    pool.write_observation(unix_timestamp as u32)?;
+
+    let mut net_amount_0: i128 = 0;
+    let mut net_amount_1: i128 = 0;
+
+    for (i, entry) in entries.iter().enumerate() {
+        let LiquidityEntry { lower_tick, upper_tick, liquidity_delta } = *entry;
+        require!(liquidity_delta != 0, ErrorCode::InsufficientInputAmount);
+        require!(
+            lower_tick < upper_tick
+                && lower_tick >= MIN_TICK
+                && upper_tick <= MAX_TICK
+                && lower_tick % pool.tick_spacing == 0
+                && upper_tick % pool.tick_spacing == 0,
+            ErrorCode::InvalidTickRange
+        );
+
+        let accounts = &ctx.remaining_accounts[i * ACCOUNTS_PER_ENTRY..(i + 1) * ACCOUNTS_PER_ENTRY];
+
+        // Each account is written back before the next one is loaded, so
+        // entries that share a tick array see each other's updates.
+        for (account, tick) in [(&accounts[0], lower_tick), (&accounts[1], upper_tick)] {
+            TickArray::init_if_needed(
+                &ctx.accounts.owner,
+                &ctx.accounts.system_program,
+                account,
+                pool_key,
+                tick,
+                pool.tick_spacing,
+            )?;
+        }
+        let (fee_growth_inside_0_x64, fee_growth_inside_1_x64) = TickArray::update_position_ticks_in_place(
+            &accounts[0],
+            &accounts[1],
+            pool,
+            lower_tick,
+            upper_tick,
+            liquidity_delta,
+        )?;
+
+        init_pda_if_needed(
+            &ctx.accounts.owner,
+            &ctx.accounts.system_program,
+            &accounts[2],
+            &[
+                b"position",
+                owner.as_ref(),
+                pool_key.as_ref(),
+                &lower_tick.to_le_bytes(),
+                &upper_tick.to_le_bytes(),
+            ],
+            Position::SPACE,
+            |bump| Position {
+                liquidity: 0,
+                tick_lower: lower_tick,
+                tick_upper: upper_tick,
+                owner,
+                pool: pool_key,
+                bump,
+                lock_until: 0,
+                operator: Pubkey::default(),
+                fee_growth_inside_0_last_x64: 0,
+                fee_growth_inside_1_last_x64: 0,
+                tokens_owed_0: 0,
+                tokens_owed_1: 0,
+                keeper: Pubkey::default(),
+            },
+        )?;
+
+        let mut position = Account::<Position>::try_from(&accounts[2])?;
+        require_keys_eq!(position.owner, owner, ErrorCode::InvalidPositionOwner);
+        if liquidity_delta < 0 {
+            position.require_unlocked(unix_timestamp)?;
+        }
+        position.accrue_fees(fee_growth_inside_0_x64, fee_growth_inside_1_x64)?;
+        position.liquidity = position
+            .liquidity
+            .checked_add_signed(liquidity_delta)
+            .ok_or(ErrorCode::InsufficientLiquidity)?;
+        position.exit(&crate::ID)?;
+
+        pool.apply_liquidity_delta(lower_tick, upper_tick, liquidity_delta)?;
+
+        let (amount_0, amount_1) = get_amounts_for_liquidity(
+            pool.sqrt_price_x96,
+            get_sqrt_price_from_tick(lower_tick)?,
+            get_sqrt_price_from_tick(upper_tick)?,
+            liquidity_delta.unsigned_abs(),
+        )?;
+
+        if liquidity_delta > 0 {
+            net_amount_0 += amount_0 as i128;
+            net_amount_1 += amount_1 as i128;
+            emit_cpi!(events::LiquidityIncreased {
+                pool: pool_key,
+                position: position.key(),
+                owner,
+                tick_lower: lower_tick,
+                tick_upper: upper_tick,
+                liquidity: liquidity_delta.unsigned_abs(),
+                amount_0,
+                amount_1,
+            });
+        } else {
+            net_amount_0 -= amount_0 as i128;
+            net_amount_1 -= amount_1 as i128;
+            emit_cpi!(events::LiquidityDecreased {
+                pool: pool_key,
+                position: position.key(),
+                owner,
+                tick_lower: lower_tick,
+                tick_upper: upper_tick,
+                liquidity: liquidity_delta.unsigned_abs(),
+                amount_0,
+                amount_1,
+            });
+        }
+    }
+
+    // Hook extras follow the per-entry accounts.
+    let hook_accounts = &ctx.remaining_accounts[entries.len() * ACCOUNTS_PER_ENTRY..];
+
+    pool.lock()?;
+    pool.exit(&crate::ID)?;
+
+    let net_amount_0 = settle(
+        pool,
+        &ctx.accounts.owner,
+        &ctx.accounts.user_token_0,
+        &ctx.accounts.pool_token_0,
+        &ctx.accounts.token_mint_0,
+        &ctx.accounts.token_program_0,
+        hook_accounts,
+        net_amount_0,
+    )?;
+    let net_amount_1 = settle(
+        pool,
+        &ctx.accounts.owner,
+        &ctx.accounts.user_token_1,
+        &ctx.accounts.pool_token_1,
+        &ctx.accounts.token_mint_1,
+        &ctx.accounts.token_program_1,
+        hook_accounts,
+        net_amount_1,
+    )?;
+
+    pool.unlock();
+
+    Ok((net_amount_0, net_amount_1))
+}
+
+/// Moves the net amount of one token: deposits are grossed up for transfer
+/// fees so the vault receives the full amount. Returns the amount charged.
+fn settle<'info>(
+    pool: &Account<'info, Pool>,
+    owner: &Signer<'info>,
+    user_token: &InterfaceAccount<'info, TokenAccount>,
+    pool_token: &InterfaceAccount<'info, TokenAccount>,
+    mint: &InterfaceAccount<'info, Mint>,
+    token_program: &Interface<'info, TokenInterface>,
+    remaining_accounts: &[AccountInfo<'info>],
+    net_amount: i128,
+) -> Result<i128> {
+    let amount = u64::try_from(net_amount.unsigned_abs()).map_err(|_| ErrorCode::ArithmeticOverflow)?;
+    if net_amount > 0 {
+        let amount = amount
+            .checked_add(get_transfer_inverse_fee(mint, amount)?)
+            .ok_or(ErrorCode::ArithmeticOverflow)?;
+        transfer_from_user_to_vault(
+            owner,
+            user_token,
+            pool_token,
+            mint,
+            token_program,
+            remaining_accounts,
+            amount,
+        )?;
+        Ok(amount as i128)
+    } else {
+        if amount > 0 {
+            transfer_from_vault_to_user(
+                pool,
+                pool_token,
+                user_token,
+                mint,
+                token_program,
+                remaining_accounts,
+                amount,
+            )?;
+        }
+        Ok(net_amount)
+    }
+}
@@ -1,12 +1,17 @@
 use anchor_lang::prelude::*;
-use anchor_spl::token::{ Token, TokenAccount}; 
+use anchor_spl::token_interface::{Mint, TokenAccount, TokenInterface};
 use crate::states::*;
+use crate::utils::ErrorCode;
 
 
 #[derive(Accounts)]
 #[instruction(lower_tick: i32, upper_tick: i32, liquidity_amount: u128)]
 pub struct Burn<'info> {
-    #[account(mut)]
+    #[account(
+        mut,
+        has_one = token_mint_0,
+        has_one = token_mint_1,
+    )]
     pub pool: Account<'info, Pool>,
 
     #[account(
@@ -28,17 +33,34 @@ pub struct Burn<'info> {
     #[account(mut)]
     pub tick_array_upper: Account<'info, TickArray>,
 
-    #[account(mut)]
-    pub pool_token_0: Account<'info, TokenAccount>,
-    #[account(mut)]
-    pub pool_token_1: Account<'info, TokenAccount>,
-    #[account(mut)]
-    pub user_token_0: Account<'info, TokenAccount>,
-    #[account(mut)]
-    pub user_token_1: Account<'info, TokenAccount>,
+    #[account(
+        mut,
+        constraint = user_token_0.mint == pool.token_mint_0 @ ErrorCode::InvalidTokenMint,
+        constraint = user_token_0.owner == owner.key() @ ErrorCode::InvalidTokenAccountOwner,
+    )]
+    pub user_token_0: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        constraint = user_token_1.mint == pool.token_mint_1 @ ErrorCode::InvalidTokenMint,
+        constraint = user_token_1.owner == owner.key() @ ErrorCode::InvalidTokenAccountOwner,
+    )]
+    pub user_token_1: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        address = pool.token_vault_0 @ ErrorCode::InvalidPoolVault,
+    )]
+    pub pool_token_0: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        address = pool.token_vault_1 @ ErrorCode::InvalidPoolVault,
+    )]
+    pub pool_token_1: InterfaceAccount<'info, TokenAccount>,
     #[account(mut)]
     pub owner: Signer<'info>,
+    pub token_mint_0: InterfaceAccount<'info, Mint>,
+    pub token_mint_1: InterfaceAccount<'info, Mint>,
     pub system_program: Program<'info, System>,
-    pub token_program: Program<'info, Token>,
+    pub token_program_0: Interface<'info, TokenInterface>,
+    pub token_program_1: Interface<'info, TokenInterface>,
     pub rent: Sysvar<'info, Rent>,
 }
\ No newline at end of file
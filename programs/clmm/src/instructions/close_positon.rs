@@ -1,14 +1,20 @@
 use anchor_lang::prelude::*;
-use anchor_spl::token::{self, Transfer};
-use anchor_spl::token::{Mint, Token, TokenAccount};
+use anchor_spl::token_interface::{Mint, TokenAccount, TokenInterface};
+use crate::events;
 use crate::states::*;
 use crate::utils::ErrorCode;
 use crate::utils::math::*;
+use crate::utils::token::*;
 
+#[event_cpi]
 #[derive(Accounts)]
-#[instruction(lower_tick: i32, upper_tick: i32, tick_array_lower_start_index: i32, tick_array_upper_start_index: i32)]
+#[instruction(lower_tick: i32, upper_tick: i32)]
 pub struct ClosePosition<'info> {
-    #[account(mut)]
+    #[account(
+        mut,
+        has_one = token_mint_0,
+        has_one = token_mint_1,
+    )]
     pub pool: Account<'info, Pool>,
 
     #[account(
@@ -16,9 +22,10 @@ pub struct ClosePosition<'info> {
         seeds = [
             b"tick_array",
             pool.key().as_ref(),
-            &tick_array_lower_start_index.to_le_bytes()
+            &TickArray::get_starting_tick_index(lower_tick, pool.tick_spacing).to_le_bytes()
         ],
-        bump
+        bump = lower_tick_array.bump,
+        constraint = lower_tick_array.pool == pool.key() @ ErrorCode::InvalidTickArrayPool,
     )]
     pub lower_tick_array: Account<'info, TickArray>,
 
@@ -27,9 +34,10 @@ pub struct ClosePosition<'info> {
         seeds = [
             b"tick_array",
             pool.key().as_ref(),
-            &tick_array_upper_start_index.to_le_bytes()
+            &TickArray::get_starting_tick_index(upper_tick, pool.tick_spacing).to_le_bytes()
         ],
-        bump
+        bump = upper_tick_array.bump,
+        constraint = upper_tick_array.pool == pool.key() @ ErrorCode::InvalidTickArrayPool,
     )]
     pub upper_tick_array: Account<'info, TickArray>,
 
@@ -38,54 +46,89 @@ pub struct ClosePosition<'info> {
         close = owner,
         seeds = [
             b"position",
-            owner.key().as_ref(),
+            position.owner.as_ref(),
             pool.key().as_ref(),
             &lower_tick.to_le_bytes(),
             &upper_tick.to_le_bytes(),
         ],
         bump = position.bump,
-        has_one = owner,
-        has_one = pool,
+        has_one = owner @ ErrorCode::InvalidPositionOwner,
+        has_one = pool @ ErrorCode::InvalidPositionRange,
+        constraint = position.is_authorized(&payer.key()) @ ErrorCode::InvalidPositionOwner,
     )]
     pub position: Account<'info, Position>,
 
-    #[account(mut)]
-    pub user_token_0: Account<'info, TokenAccount>,
-    #[account(mut)]
-    pub user_token_1: Account<'info, TokenAccount>,
+    // The owner may withdraw to any account of the right mint; an operator
+    // may only withdraw to the owner's own accounts.
+    #[account(
+        mut,
+        constraint = recipient_token_0.mint == pool.token_mint_0 @ ErrorCode::InvalidTokenMint,
+        constraint = payer.key() == position.owner
+            || recipient_token_0.owner == position.owner @ ErrorCode::InvalidTokenAccountOwner,
+    )]
+    pub recipient_token_0: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        constraint = recipient_token_1.mint == pool.token_mint_1 @ ErrorCode::InvalidTokenMint,
+        constraint = payer.key() == position.owner
+            || recipient_token_1.owner == position.owner @ ErrorCode::InvalidTokenAccountOwner,
+    )]
+    pub recipient_token_1: InterfaceAccount<'info, TokenAccount>,
     
     #[account(
         mut,
-        constraint = pool_token_0.mint == pool.token_mint_0
+        address = pool.token_vault_0 @ ErrorCode::InvalidPoolVault,
     )]
-    pub pool_token_0: Account<'info, TokenAccount>,
+    pub pool_token_0: InterfaceAccount<'info, TokenAccount>,
     
     #[account(
         mut,
-        constraint = pool_token_1.mint == pool.token_mint_1
+        address = pool.token_vault_1 @ ErrorCode::InvalidPoolVault,
     )]
-    pub pool_token_1: Account<'info, TokenAccount>,
+    pub pool_token_1: InterfaceAccount<'info, TokenAccount>,
 
+    /// CHECK: the position owner, who gets the account's rent back; checked
+    /// by `has_one` on the position.
     #[account(mut)]
-    pub owner: Signer<'info>,
+    pub owner: UncheckedAccount<'info>,
+
+    /// The owner or the position's operator.
+    pub payer: Signer<'info>,
     
-    pub token_program: Program<'info, Token>,
+    pub token_mint_0: InterfaceAccount<'info, Mint>,
+    pub token_mint_1: InterfaceAccount<'info, Mint>,
+    pub token_program_0: Interface<'info, TokenInterface>,
+    pub token_program_1: Interface<'info, TokenInterface>,
 }
 
-
-pub fn close_position(
-    ctx: Context<ClosePosition>,
+/// Withdraws all of the position's liquidity together with its accrued fees
+/// and closes it, returning the rent to the owner. Fails while the position
+/// is locked. Returns the amounts paid out of the vaults. The position's
+/// operator may close it on the owner's behalf.
+pub fn close_position<'info>(
+    ctx: Context<'_, '_, '_, 'info, ClosePosition<'info>>,
     lower_tick: i32,
     upper_tick: i32,
-    _tick_array_lower_start_index: i32,
-    _tick_array_upper_start_index: i32
 ) -> Result<(u64, u64)> {
     let pool = &mut ctx.accounts.pool;
-    let position = &ctx.accounts.position;
+    pool.require_not_paused(PAUSE_WITHDRAW)?;
+    let position = &mut ctx.accounts.position;
+    position.require_unlocked(Clock::get()?.unix_timestamp)?;
 
     let liquidity_to_remove = position.liquidity;
     require!(liquidity_to_remove > 0, ErrorCode::NoLiquidityToRemove);
 
+    let (fee_growth_inside_0_x64, fee_growth_inside_1_x64) = TickArray::update_position_ticks(
+        &mut ctx.accounts.lower_tick_array,
+        &mut ctx.accounts.upper_tick_array,
+        pool,
+        lower_tick,
+        upper_tick,
+        -(liquidity_to_remove as i128),
+    )?;
+    position.accrue_fees(fee_growth_inside_0_x64, fee_growth_inside_1_x64)?;
+    let (fees_0, fees_1) = position.take_fees();
+
     let (amount_0, amount_1) = get_amounts_for_liquidity(
         pool.sqrt_price_x96,
         get_sqrt_price_from_tick(lower_tick)?,
@@ -93,59 +136,59 @@ pub fn close_position(
         liquidity_to_remove,
     )?;
 
-    let lower_tick_array = &mut ctx.accounts.lower_tick_array;
-    let upper_tick_array = &mut ctx.accounts.upper_tick_array;
-
-    let lower_tick_info =
-        lower_tick_array.get_tick_info_mutable(lower_tick, pool.tick_spacing)?;
-    lower_tick_info.update_liquidity(-(liquidity_to_remove as i128), true)?;
-
-    let upper_tick_info =
-        upper_tick_array.get_tick_info_mutable(upper_tick, pool.tick_spacing)?;
-    upper_tick_info.update_liquidity(-(liquidity_to_remove as i128), false)?;
-
-    pool.global_liquidity = pool
-        .global_liquidity
-        .checked_sub(liquidity_to_remove)
-        .ok_or(ErrorCode::ArithmeticOverflow)?;
-
-    let pool_seeds = &[
-        b"pool".as_ref(),
-        pool.token_mint_0.as_ref(),
-        pool.token_mint_1.as_ref(),
-        &[pool.bump],
-    ];
-    let signer_seeds = &[&pool_seeds[..]];
-
-    if amount_0 > 0 {
-        token::transfer(
-            CpiContext::new_with_signer(
-                ctx.accounts.token_program.to_account_info(),
-                Transfer {
-                    from: ctx.accounts.pool_token_0.to_account_info(),
-                    to: ctx.accounts.user_token_0.to_account_info(),
-                    authority: pool.to_account_info(),
-                },
-                signer_seeds,
-            ),
-            amount_0,
+    // Accrued fees are paid out with the liquidity since the account closes.
+    let total_0 = amount_0.checked_add(fees_0).ok_or(ErrorCode::ArithmeticOverflow)?;
+    let total_1 = amount_1.checked_add(fees_1).ok_or(ErrorCode::ArithmeticOverflow)?;
+
+    pool.write_observation(Clock::get()?.unix_timestamp as u32)?;
+    pool.apply_liquidity_delta(lower_tick, upper_tick, -(liquidity_to_remove as i128))?;
+
+    pool.lock()?;
+    pool.exit(&crate::ID)?;
+
+    if total_0 > 0 {
+        transfer_from_vault_to_user(
+            pool,
+            &ctx.accounts.pool_token_0,
+            &ctx.accounts.recipient_token_0,
+            &ctx.accounts.token_mint_0,
+            &ctx.accounts.token_program_0,
+            ctx.remaining_accounts,
+            total_0,
         )?;
     }
 
-    if amount_1 > 0 {
-        token::transfer(
-            CpiContext::new_with_signer(
-                ctx.accounts.token_program.to_account_info(),
-                Transfer {
-                    from: ctx.accounts.pool_token_1.to_account_info(),
-                    to: ctx.accounts.user_token_1.to_account_info(),
-                    authority: pool.to_account_info(),
-                },
-                signer_seeds,
-            ),
-            amount_1,
+    if total_1 > 0 {
+        transfer_from_vault_to_user(
+            pool,
+            &ctx.accounts.pool_token_1,
+            &ctx.accounts.recipient_token_1,
+            &ctx.accounts.token_mint_1,
+            &ctx.accounts.token_program_1,
+            ctx.remaining_accounts,
+            total_1,
         )?;
     }
 
-    Ok((amount_0, amount_1))
-}
\ No newline at end of file
+    pool.unlock();
+
+    emit_cpi!(events::LiquidityDecreased {
+        pool: pool.key(),
+        position: position.key(),
+        owner: position.owner,
+        tick_lower: lower_tick,
+        tick_upper: upper_tick,
+        liquidity: liquidity_to_remove,
+        amount_0,
+        amount_1,
+    });
+    emit_cpi!(events::FeesCollected {
+        pool: pool.key(),
+        position: position.key(),
+        owner: position.owner,
+        amount_0: fees_0,
+        amount_1: fees_1,
+    });
+
+    Ok((total_0, total_1))
+}
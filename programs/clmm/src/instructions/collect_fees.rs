@@ -0,0 +1,136 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::{Mint, TokenAccount, TokenInterface};
+use crate::events;
+use crate::states::*;
+use crate::utils::ErrorCode;
+use crate::utils::token::*;
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct CollectFees<'info> {
+    #[account(
+        mut,
+        has_one = token_mint_0,
+        has_one = token_mint_1,
+    )]
+    pub pool: Account<'info, Pool>,
+
+    #[account(
+        mut,
+        has_one = pool @ ErrorCode::InvalidPositionRange,
+        constraint = position.is_authorized(&payer.key()) @ ErrorCode::InvalidPositionOwner,
+    )]
+    pub position: Account<'info, Position>,
+
+    #[account(
+        seeds = [
+            b"tick_array",
+            pool.key().as_ref(),
+            &TickArray::get_starting_tick_index(position.tick_lower, pool.tick_spacing).to_le_bytes()
+        ],
+        bump = lower_tick_array.bump,
+    )]
+    pub lower_tick_array: Account<'info, TickArray>,
+
+    #[account(
+        seeds = [
+            b"tick_array",
+            pool.key().as_ref(),
+            &TickArray::get_starting_tick_index(position.tick_upper, pool.tick_spacing).to_le_bytes()
+        ],
+        bump = upper_tick_array.bump,
+    )]
+    pub upper_tick_array: Account<'info, TickArray>,
+
+    // Fees always go to the position owner, even when an operator signs.
+    #[account(
+        mut,
+        constraint = user_token_0.mint == pool.token_mint_0 @ ErrorCode::InvalidTokenMint,
+        constraint = user_token_0.owner == position.owner @ ErrorCode::InvalidTokenAccountOwner,
+    )]
+    pub user_token_0: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        constraint = user_token_1.mint == pool.token_mint_1 @ ErrorCode::InvalidTokenMint,
+        constraint = user_token_1.owner == position.owner @ ErrorCode::InvalidTokenAccountOwner,
+    )]
+    pub user_token_1: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        address = pool.token_vault_0 @ ErrorCode::InvalidPoolVault,
+    )]
+    pub pool_token_0: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        address = pool.token_vault_1 @ ErrorCode::InvalidPoolVault,
+    )]
+    pub pool_token_1: InterfaceAccount<'info, TokenAccount>,
+
+    /// The owner or the position's operator.
+    pub payer: Signer<'info>,
+
+    pub token_mint_0: InterfaceAccount<'info, Mint>,
+    pub token_mint_1: InterfaceAccount<'info, Mint>,
+    pub token_program_0: Interface<'info, TokenInterface>,
+    pub token_program_1: Interface<'info, TokenInterface>,
+}
+
+/// Pays the position's accrued fees to its owner without touching its
+/// liquidity, so it also works while the position is locked. Returns the
+/// amounts paid out of the vaults.
+pub fn collect_fees<'info>(
+    ctx: Context<'_, '_, '_, 'info, CollectFees<'info>>,
+) -> Result<(u64, u64)> {
+    let pool = &mut ctx.accounts.pool;
+    pool.require_not_paused(PAUSE_COLLECT_FEES)?;
+
+    let position = &mut ctx.accounts.position;
+    let (fee_growth_inside_0_x64, fee_growth_inside_1_x64) = TickArray::get_fee_growth_inside(
+        &ctx.accounts.lower_tick_array,
+        &ctx.accounts.upper_tick_array,
+        pool,
+        position.tick_lower,
+        position.tick_upper,
+    )?;
+    position.accrue_fees(fee_growth_inside_0_x64, fee_growth_inside_1_x64)?;
+    let (amount_0, amount_1) = position.take_fees();
+
+    pool.lock()?;
+    pool.exit(&crate::ID)?;
+
+    if amount_0 > 0 {
+        transfer_from_vault_to_user(
+            pool,
+            &ctx.accounts.pool_token_0,
+            &ctx.accounts.user_token_0,
+            &ctx.accounts.token_mint_0,
+            &ctx.accounts.token_program_0,
+            ctx.remaining_accounts,
+            amount_0,
+        )?;
+    }
+
+    if amount_1 > 0 {
+        transfer_from_vault_to_user(
+            pool,
+            &ctx.accounts.pool_token_1,
+            &ctx.accounts.user_token_1,
+            &ctx.accounts.token_mint_1,
+            &ctx.accounts.token_program_1,
+            ctx.remaining_accounts,
+            amount_1,
+        )?;
+    }
+
+    pool.unlock();
+
+    emit_cpi!(events::FeesCollected {
+        pool: pool.key(),
+        position: position.key(),
+        owner: position.owner,
+        amount_0,
+        amount_1,
+    });
+
+    Ok((amount_0, amount_1))
+}
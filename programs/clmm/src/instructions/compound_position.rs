@@ -0,0 +1,286 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::{Mint, TokenAccount, TokenInterface};
+use crate::events;
+use crate::instructions::swap::{swap_in_vaults, VaultSwap};
+use crate::states::*;
+use crate::utils::ErrorCode;
+use crate::utils::math::*;
+use crate::utils::token::*;
+
+/// Share of the compounded fees paid to a keeper other than the owner.
+pub const COMPOUND_BOUNTY_BPS: u64 = 100;
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct CompoundPosition<'info> {
+    #[account(
+        mut,
+        has_one = token_mint_0,
+        has_one = token_mint_1,
+    )]
+    pub pool: Account<'info, Pool>,
+
+    #[account(
+        mut,
+        has_one = pool @ ErrorCode::InvalidPositionRange,
+        constraint = position.can_compound(&keeper.key()) @ ErrorCode::InvalidPositionOwner,
+    )]
+    pub position: Account<'info, Position>,
+
+    #[account(
+        mut,
+        seeds = [
+            b"tick_array",
+            pool.key().as_ref(),
+            &TickArray::get_starting_tick_index(position.tick_lower, pool.tick_spacing).to_le_bytes()
+        ],
+        bump = lower_tick_array.bump,
+        constraint = lower_tick_array.pool == pool.key() @ ErrorCode::InvalidTickArrayPool,
+    )]
+    pub lower_tick_array: Account<'info, TickArray>,
+
+    #[account(
+        mut,
+        seeds = [
+            b"tick_array",
+            pool.key().as_ref(),
+            &TickArray::get_starting_tick_index(position.tick_upper, pool.tick_spacing).to_le_bytes()
+        ],
+        bump = upper_tick_array.bump,
+        constraint = upper_tick_array.pool == pool.key() @ ErrorCode::InvalidTickArrayPool,
+    )]
+    pub upper_tick_array: Account<'info, TickArray>,
+
+    // The bounty, if any, goes to the keeper's own token accounts.
+    #[account(
+        mut,
+        constraint = keeper_token_0.mint == pool.token_mint_0 @ ErrorCode::InvalidTokenMint,
+        constraint = keeper_token_0.owner == keeper.key() @ ErrorCode::InvalidTokenAccountOwner,
+    )]
+    pub keeper_token_0: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        constraint = keeper_token_1.mint == pool.token_mint_1 @ ErrorCode::InvalidTokenMint,
+        constraint = keeper_token_1.owner == keeper.key() @ ErrorCode::InvalidTokenAccountOwner,
+    )]
+    pub keeper_token_1: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        address = pool.token_vault_0 @ ErrorCode::InvalidPoolVault,
+    )]
+    pub pool_token_0: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        address = pool.token_vault_1 @ ErrorCode::InvalidPoolVault,
+    )]
+    pub pool_token_1: InterfaceAccount<'info, TokenAccount>,
+
+    /// The owner, the position's operator or its keeper.
+    pub keeper: Signer<'info>,
+
+    pub token_mint_0: InterfaceAccount<'info, Mint>,
+    pub token_mint_1: InterfaceAccount<'info, Mint>,
+    pub token_program_0: Interface<'info, TokenInterface>,
+    pub token_program_1: Interface<'info, TokenInterface>,
+}
+
+/// Reinvests the position's accrued fees into its own range. The excess side
+/// is swapped inside the pool first; whatever the range still cannot use stays
+/// owed to the position. An operator or keeper compounding for the owner
+/// earns `COMPOUND_BOUNTY_BPS` of the fees. Returns the liquidity added.
+pub fn compound_position<'info>(
+    ctx: Context<'_, '_, 'info, 'info, CompoundPosition<'info>>,
+    min_liquidity: u128,
+) -> Result<u128> {
+    let pool = &mut ctx.accounts.pool;
+    pool.require_not_paused(PAUSE_COLLECT_FEES | PAUSE_DEPOSIT)?;
+    let now = Clock::get()?.unix_timestamp as u32;
+
+    let position = &mut ctx.accounts.position;
+    let (fee_growth_inside_0_x64, fee_growth_inside_1_x64) = TickArray::get_fee_growth_inside(
+        &ctx.accounts.lower_tick_array,
+        &ctx.accounts.upper_tick_array,
+        pool,
+        position.tick_lower,
+        position.tick_upper,
+    )?;
+    position.accrue_fees(fee_growth_inside_0_x64, fee_growth_inside_1_x64)?;
+    let (fees_0, fees_1) = position.take_fees();
+    require!(fees_0 > 0 || fees_1 > 0, ErrorCode::NoFeesToCompound);
+
+    let keeper = ctx.accounts.keeper.key();
+    let (bounty_0, bounty_1) = if keeper == position.owner {
+        (0, 0)
+    } else {
+        (
+            (fees_0 as u128 * COMPOUND_BOUNTY_BPS as u128 / 10_000) as u64,
+            (fees_1 as u128 * COMPOUND_BOUNTY_BPS as u128 / 10_000) as u64,
+        )
+    };
+    let mut amount_0 = fees_0 - bounty_0;
+    let mut amount_1 = fees_1 - bounty_1;
+
+    let lower_tick = position.tick_lower;
+    let upper_tick = position.tick_upper;
+    let lower_sqrt_price_x96 = get_sqrt_price_from_tick(lower_tick)?;
+    let upper_sqrt_price_x96 = get_sqrt_price_from_tick(upper_tick)?;
+
+    // Whatever the current ratio leaves unused on one side is swapped so both
+    // sides match, the same way `zap_in` splits a single-token deposit.
+    let liquidity = get_liquidity_for_amounts(
+        pool.sqrt_price_x96,
+        lower_sqrt_price_x96,
+        upper_sqrt_price_x96,
+        amount_0,
+        amount_1,
+    )?;
+    let (used_0, used_1) = get_amounts_for_liquidity(
+        pool.sqrt_price_x96,
+        lower_sqrt_price_x96,
+        upper_sqrt_price_x96,
+        liquidity,
+    )?;
+    let (excess, excess_is_token_0) = if amount_0 - used_0 >= amount_1 - used_1 {
+        (amount_0 - used_0, true)
+    } else {
+        (amount_1 - used_1, false)
+    };
+    let swap_amount = get_zap_swap_amount(
+        pool.sqrt_price_x96,
+        lower_sqrt_price_x96,
+        upper_sqrt_price_x96,
+        excess,
+        excess_is_token_0,
+        pool.fee_rate(now),
+    )?;
+
+    let mut swap_events = None;
+    if swap_amount > 0 {
+        let tick_arrays: Vec<AccountInfo<'info>> = [
+            ctx.accounts.lower_tick_array.to_account_info(),
+            ctx.accounts.upper_tick_array.to_account_info(),
+        ]
+        .into_iter()
+        .chain(ctx.remaining_accounts.iter().cloned())
+        .collect();
+        let VaultSwap {
+            amount_out,
+            swap_event,
+            filled_limit_orders,
+        } = swap_in_vaults(
+            pool,
+            keeper,
+            &tick_arrays,
+            swap_amount,
+            excess_is_token_0,
+            now,
+        )?;
+        let (available_in, available_out) = if excess_is_token_0 {
+            (&mut amount_0, &mut amount_1)
+        } else {
+            (&mut amount_1, &mut amount_0)
+        };
+        *available_in -= swap_amount;
+        *available_out = available_out
+            .checked_add(amount_out)
+            .ok_or(ErrorCode::ArithmeticOverflow)?;
+        // Ticks the swap crossed may live in the position's own arrays.
+        ctx.accounts.lower_tick_array.reload()?;
+        ctx.accounts.upper_tick_array.reload()?;
+        swap_events = Some((swap_event, filled_limit_orders));
+    }
+
+    let liquidity = get_liquidity_for_amounts(
+        pool.sqrt_price_x96,
+        lower_sqrt_price_x96,
+        upper_sqrt_price_x96,
+        amount_0,
+        amount_1,
+    )?;
+    require!(
+        liquidity > 0 && liquidity >= min_liquidity,
+        ErrorCode::SlippageExceeded
+    );
+    let (used_0, used_1) = get_amounts_for_liquidity(
+        pool.sqrt_price_x96,
+        lower_sqrt_price_x96,
+        upper_sqrt_price_x96,
+        liquidity,
+    )?;
+
+    let (fee_growth_inside_0_x64, fee_growth_inside_1_x64) = TickArray::update_position_ticks(
+        &mut ctx.accounts.lower_tick_array,
+        &mut ctx.accounts.upper_tick_array,
+        pool,
+        lower_tick,
+        upper_tick,
+        liquidity as i128,
+    )?;
+
+    // Fees from the swap above are owed on the old liquidity, alongside
+    // whatever the range could not use.
+    position.accrue_fees(fee_growth_inside_0_x64, fee_growth_inside_1_x64)?;
+    position.liquidity = position
+        .liquidity
+        .checked_add(liquidity)
+        .ok_or(ErrorCode::ArithmeticOverflow)?;
+    position.tokens_owed_0 = position
+        .tokens_owed_0
+        .checked_add(amount_0.checked_sub(used_0).ok_or(ErrorCode::ArithmeticOverflow)?)
+        .ok_or(ErrorCode::ArithmeticOverflow)?;
+    position.tokens_owed_1 = position
+        .tokens_owed_1
+        .checked_add(amount_1.checked_sub(used_1).ok_or(ErrorCode::ArithmeticOverflow)?)
+        .ok_or(ErrorCode::ArithmeticOverflow)?;
+
+    pool.write_observation(now)?;
+    pool.apply_liquidity_delta(lower_tick, upper_tick, liquidity as i128)?;
+
+    pool.lock()?;
+    pool.exit(&crate::ID)?;
+
+    if bounty_0 > 0 {
+        transfer_from_vault_to_user(
+            pool,
+            &ctx.accounts.pool_token_0,
+            &ctx.accounts.keeper_token_0,
+            &ctx.accounts.token_mint_0,
+            &ctx.accounts.token_program_0,
+            ctx.remaining_accounts,
+            bounty_0,
+        )?;
+    }
+
+    if bounty_1 > 0 {
+        transfer_from_vault_to_user(
+            pool,
+            &ctx.accounts.pool_token_1,
+            &ctx.accounts.keeper_token_1,
+            &ctx.accounts.token_mint_1,
+            &ctx.accounts.token_program_1,
+            ctx.remaining_accounts,
+            bounty_1,
+        )?;
+    }
+
+    pool.unlock();
+
+    if let Some((swap_event, filled_limit_orders)) = swap_events {
+        emit_cpi!(swap_event);
+        for filled in filled_limit_orders {
+            emit_cpi!(filled);
+        }
+    }
+    emit_cpi!(events::PositionCompounded {
+        pool: pool.key(),
+        position: position.key(),
+        keeper,
+        fees_0,
+        fees_1,
+        bounty_0,
+        bounty_1,
+        liquidity,
+    });
+
+    Ok(liquidity)
+}
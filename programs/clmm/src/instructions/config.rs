@@ -0,0 +1,81 @@
+use anchor_lang::prelude::*;
+use crate::program::Clmm;
+use crate::states::*;
+use crate::utils::ErrorCode;
+
+#[derive(Accounts)]
+pub struct InitializeConfig<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(
+        init,
+        payer = authority,
+        space = Config::SPACE,
+        seeds = [b"config".as_ref()],
+        bump,
+    )]
+    pub config: Account<'info, Config>,
+
+    #[account(
+        constraint = program.programdata_address()? == Some(program_data.key()) @ ErrorCode::InvalidConfigAuthority,
+    )]
+    pub program: Program<'info, Clmm>,
+
+    #[account(
+        constraint = program_data.upgrade_authority_address == Some(authority.key()) @ ErrorCode::InvalidConfigAuthority,
+    )]
+    pub program_data: Account<'info, ProgramData>,
+
+    pub system_program: Program<'info, System>,
+}
+
+pub fn initialize_config(ctx: Context<InitializeConfig>) -> Result<()> {
+    let config = &mut ctx.accounts.config;
+
+    config.authority = ctx.accounts.authority.key();
+    config.trusted_transfer_hook_programs = Vec::new();
+    config.allowed_freezable_mints = Vec::new();
+    config.bump = ctx.bumps.config;
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct UpdateConfig<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"config".as_ref()],
+        bump = config.bump,
+        has_one = authority @ ErrorCode::InvalidConfigAuthority,
+    )]
+    pub config: Account<'info, Config>,
+}
+
+pub fn set_trusted_transfer_hook_programs(
+    ctx: Context<UpdateConfig>,
+    programs: Vec<Pubkey>,
+) -> Result<()> {
+    require!(
+        programs.len() <= MAX_TRUSTED_TRANSFER_HOOK_PROGRAMS,
+        ErrorCode::TooManyTransferHookPrograms
+    );
+
+    ctx.accounts.config.trusted_transfer_hook_programs = programs;
+
+    Ok(())
+}
+
+
+pub fn set_allowed_freezable_mints(ctx: Context<UpdateConfig>, mints: Vec<Pubkey>) -> Result<()> {
+    require!(
+        mints.len() <= MAX_ALLOWED_FREEZABLE_MINTS,
+        ErrorCode::TooManyFreezableMints
+    );
+
+    ctx.accounts.config.allowed_freezable_mints = mints;
+
+    Ok(())
+}
@@ -1,11 +1,14 @@
 use anchor_lang::prelude::*;
-use anchor_spl::token::{self, Transfer};
-use anchor_spl::token::{Mint, Token, TokenAccount};
+use anchor_spl::token_interface::{Mint, TokenAccount, TokenInterface};
+use crate::events;
 use crate::states::*;
 use crate::utils::ErrorCode;
 use crate::utils::math::*;
+use crate::utils::token::*;
 
+#[event_cpi]
 #[derive(Accounts)]
+#[instruction(lower_tick: i32, upper_tick: i32)]
 pub struct DecreaseLiquidity<'info> {
     #[account(
         mut,
@@ -14,60 +17,102 @@ pub struct DecreaseLiquidity<'info> {
     )]
     pub pool: Account<'info, Pool>,
 
-    #[account()]
+    #[account(
+        mut,
+        seeds = [
+            b"tick_array",
+            pool.key().as_ref(),
+            &TickArray::get_starting_tick_index(lower_tick, pool.tick_spacing).to_le_bytes()
+        ],
+        bump = lower_tick_array.bump,
+        constraint = lower_tick_array.pool == pool.key() @ ErrorCode::InvalidTickArrayPool,
+        constraint = lower_tick_array.starting_tick
+            == TickArray::get_starting_tick_index(lower_tick, pool.tick_spacing)
+            @ ErrorCode::InvalidTickArrayStartIndex,
+    )]
     pub lower_tick_array: Account<'info, TickArray>,
 
-    #[account()]
+    #[account(
+        mut,
+        seeds = [
+            b"tick_array",
+            pool.key().as_ref(),
+            &TickArray::get_starting_tick_index(upper_tick, pool.tick_spacing).to_le_bytes()
+        ],
+        bump = upper_tick_array.bump,
+        constraint = upper_tick_array.pool == pool.key() @ ErrorCode::InvalidTickArrayPool,
+        constraint = upper_tick_array.starting_tick
+            == TickArray::get_starting_tick_index(upper_tick, pool.tick_spacing)
+            @ ErrorCode::InvalidTickArrayStartIndex,
+    )]
     pub upper_tick_array: Account<'info, TickArray>,
 
     #[account(
+        mut,
+        seeds = [
+            b"position",
+            position.owner.as_ref(),
+            pool.key().as_ref(),
+            &lower_tick.to_le_bytes(),
+            &upper_tick.to_le_bytes(),
+        ],
+        bump = position.bump,
         constraint = position.pool == pool.key() @ ErrorCode::InvalidPositionRange,
-        constraint = position.owner == payer.key() @ ErrorCode::InvalidPositionOwner,
+        constraint = position.is_authorized(&payer.key()) @ ErrorCode::InvalidPositionOwner,
     )]
     pub position: Account<'info, Position>,
 
+    // The owner may withdraw to any account of the right mint; an operator
+    // may only withdraw to the owner's own accounts.
     #[account(
         mut,
-        token::mint = token_mint_0
+        constraint = recipient_token_0.mint == pool.token_mint_0 @ ErrorCode::InvalidTokenMint,
+        constraint = payer.key() == position.owner
+            || recipient_token_0.owner == position.owner @ ErrorCode::InvalidTokenAccountOwner,
     )]
-    pub user_token_0: Account<'info, TokenAccount>,
+    pub recipient_token_0: InterfaceAccount<'info, TokenAccount>,
 
     #[account(
         mut,
-        token::mint = token_mint_1
+        constraint = recipient_token_1.mint == pool.token_mint_1 @ ErrorCode::InvalidTokenMint,
+        constraint = payer.key() == position.owner
+            || recipient_token_1.owner == position.owner @ ErrorCode::InvalidTokenAccountOwner,
     )]
-    pub user_token_1: Account<'info, TokenAccount>,
+    pub recipient_token_1: InterfaceAccount<'info, TokenAccount>,
 
     #[account(
         mut,
-        token::mint = token_mint_0
+        address = pool.token_vault_0 @ ErrorCode::InvalidPoolVault,
     )]
-    pub pool_token_0: Account<'info, TokenAccount>,
+    pub pool_token_0: InterfaceAccount<'info, TokenAccount>,
 
     #[account(
-        mut, 
-        token::mint = token_mint_1
+        mut,
+        address = pool.token_vault_1 @ ErrorCode::InvalidPoolVault,
     )]
-    pub pool_token_1: Account<'info, TokenAccount>,
+    pub pool_token_1: InterfaceAccount<'info, TokenAccount>,
 
     #[account(mut)]
     pub payer: Signer<'info>,
 
-    pub token_mint_0: Account<'info, Mint>,
-    pub token_mint_1: Account<'info, Mint>,
+    pub token_mint_0: InterfaceAccount<'info, Mint>,
+    pub token_mint_1: InterfaceAccount<'info, Mint>,
     pub system_program: Program<'info, System>,
-    pub token_program: Program<'info, Token>,
+    pub token_program_0: Interface<'info, TokenInterface>,
+    pub token_program_1: Interface<'info, TokenInterface>,
     pub rent: Sysvar<'info, Rent>,
 }
 
-pub fn decrease_liquidity(
-    ctx:Context<DecreaseLiquidity>,
+pub fn decrease_liquidity<'info>(
+    ctx: Context<'_, '_, '_, 'info, DecreaseLiquidity<'info>>,
     liquidity_amount: u128,
     lower_tick: i32,
     upper_tick: i32,
 ) -> Result<(u64, u64)> {
     let pool = &mut ctx.accounts.pool;
+    pool.require_not_paused(PAUSE_WITHDRAW)?;
     let position = &mut ctx.accounts.position;
+    position.require_unlocked(Clock::get()?.unix_timestamp)?;
 
     require!(
         lower_tick < upper_tick
@@ -81,17 +126,16 @@ pub fn decrease_liquidity(
         ErrorCode::MintRangeMustCoverCurrentPrice
     );
 
-    let lower_tick_array = &mut ctx.accounts.lower_tick_array;
-    let upper_tick_array = &mut ctx.accounts.upper_tick_array;
-
-    let lower_tick_info =
-        lower_tick_array.get_tick_info_mutable(lower_tick, pool.tick_spacing)?;
-    let upper_tick_info =
-        upper_tick_array.get_tick_info_mutable(upper_tick, pool.tick_spacing)?;
-
-    lower_tick_info.update_liquidity(liquidity_amount as i128, true)?;
-    upper_tick_info.update_liquidity(liquidity_amount as i128, false)?;
+    let (fee_growth_inside_0_x64, fee_growth_inside_1_x64) = TickArray::update_position_ticks(
+        &mut ctx.accounts.lower_tick_array,
+        &mut ctx.accounts.upper_tick_array,
+        pool,
+        lower_tick,
+        upper_tick,
+        -(liquidity_amount as i128),
+    )?;
 
+    position.accrue_fees(fee_growth_inside_0_x64, fee_growth_inside_1_x64)?;
     position.liquidity = position.liquidity.checked_sub(liquidity_amount).ok_or(ErrorCode::ArithmeticOverflow)?;
 
     let (amount_0, amount_1) = get_amounts_for_liquidity(
@@ -102,53 +146,48 @@ pub fn decrease_liquidity(
     )?;
 
 
-    pool.global_liquidity = pool
-        .global_liquidity
-        .checked_sub(liquidity_amount)
-        .ok_or(ErrorCode::ArithmeticOverflow)?;
+    pool.write_observation(Clock::get()?.unix_timestamp as u32)?;
+    pool.apply_liquidity_delta(lower_tick, upper_tick, -(liquidity_amount as i128))?;
+
+    pool.lock()?;
+    pool.exit(&crate::ID)?;
 
     if amount_0 > 0 {
-        let signer_seeds: &[&[&[u8]]] = &[&[
-            b"pool".as_ref(),
-            pool.token_mint_0.as_ref(),
-            pool.token_mint_1.as_ref(),
-            &pool.tick_spacing.to_le_bytes(),
-            &[pool.bump],
-        ]];
-
-        let ctx = CpiContext:: new_with_signer(
-            ctx.accounts.token_program.to_account_info(),
-            Transfer {
-                from: ctx.accounts.pool_token_0.to_account_info(),
-                to: ctx.accounts.user_token_0.to_account_info(),
-                authority: pool.to_account_info()
-            },
-            signer_seeds,
-        );
-
-        token::transfer(ctx, amount_0)?;
+        transfer_from_vault_to_user(
+            pool,
+            &ctx.accounts.pool_token_0,
+            &ctx.accounts.recipient_token_0,
+            &ctx.accounts.token_mint_0,
+            &ctx.accounts.token_program_0,
+            ctx.remaining_accounts,
+            amount_0,
+        )?;
     }
 
     if amount_1 > 0 {
-        let signer_seeds: &[&[&[u8]]]  = &[&[
-            b"pool".as_ref(),
-            pool.token_mint_0.as_ref(),
-            pool.token_mint_1.as_ref(),
-            &pool.tick_spacing.to_le_bytes(),
-            &[pool.bump],
-        ]];
-
-        let context = CpiContext::new_with_signer(
-            ctx.accounts.token_program.to_account_info(), 
-            Transfer {
-                from:ctx.accounts.pool_token_1.to_account_info(),
-                to: ctx.accounts.user_token_1.to_account_info(),
-                authority: pool.to_account_info(),
-            }, 
-            signer_seeds,
-        );
-        token::transfer(context, amount_1)?;
+        transfer_from_vault_to_user(
+            pool,
+            &ctx.accounts.pool_token_1,
+            &ctx.accounts.recipient_token_1,
+            &ctx.accounts.token_mint_1,
+            &ctx.accounts.token_program_1,
+            ctx.remaining_accounts,
+            amount_1,
+        )?;
     }
 
+    pool.unlock();
+
+    emit_cpi!(events::LiquidityDecreased {
+        pool: pool.key(),
+        position: position.key(),
+        owner: position.owner,
+        tick_lower: lower_tick,
+        tick_upper: upper_tick,
+        liquidity: liquidity_amount,
+        amount_0,
+        amount_1,
+    });
+
     Ok((amount_0, amount_1))
 }   
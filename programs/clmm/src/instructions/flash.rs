@@ -0,0 +1,162 @@
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::hash::hash;
+use anchor_lang::solana_program::instruction::{AccountMeta, Instruction};
+use anchor_lang::solana_program::program::invoke;
+use anchor_spl::token_interface::{Mint, TokenAccount, TokenInterface};
+use crate::events;
+use crate::states::*;
+use crate::utils::ErrorCode;
+use crate::utils::math::*;
+use crate::utils::token::*;
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct Flash<'info> {
+    #[account(
+        mut,
+        has_one = token_mint_0,
+        has_one = token_mint_1,
+    )]
+    pub pool: Account<'info, Pool>,
+
+    #[account(
+        mut,
+        address = pool.token_vault_0 @ ErrorCode::InvalidPoolVault,
+    )]
+    pub pool_token_0: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        address = pool.token_vault_1 @ ErrorCode::InvalidPoolVault,
+    )]
+    pub pool_token_1: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        constraint = recipient_token_0.mint == pool.token_mint_0 @ ErrorCode::InvalidTokenMint,
+    )]
+    pub recipient_token_0: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        constraint = recipient_token_1.mint == pool.token_mint_1 @ ErrorCode::InvalidTokenMint,
+    )]
+    pub recipient_token_1: InterfaceAccount<'info, TokenAccount>,
+
+    /// CHECK: borrower program, invoked with `remaining_accounts` and must repay the vaults.
+    #[account(
+        executable,
+        constraint = callback_program.key() != crate::ID @ ErrorCode::InvalidFlashCallbackProgram,
+    )]
+    pub callback_program: UncheckedAccount<'info>,
+
+    pub payer: Signer<'info>,
+    pub token_mint_0: InterfaceAccount<'info, Mint>,
+    pub token_mint_1: InterfaceAccount<'info, Mint>,
+    pub token_program_0: Interface<'info, TokenInterface>,
+    pub token_program_1: Interface<'info, TokenInterface>,
+}
+
+/// Instruction data sent to the borrower: the Anchor discriminator of
+/// `flash_callback(fee_0: u64, fee_1: u64, data: Vec<u8>)` followed by its args.
+fn flash_callback_data(fee_0: u64, fee_1: u64, data: Vec<u8>) -> Result<Vec<u8>> {
+    let mut ix_data = hash(b"global:flash_callback").to_bytes()[..8].to_vec();
+    (fee_0, fee_1, data).serialize(&mut ix_data)?;
+    Ok(ix_data)
+}
+
+pub fn flash<'info>(
+    ctx: Context<'_, '_, '_, 'info, Flash<'info>>,
+    amount_0: u64,
+    amount_1: u64,
+    data: Vec<u8>,
+) -> Result<()> {
+    let pool = &mut ctx.accounts.pool;
+    pool.require_not_paused(PAUSE_FLASH)?;
+
+    require!(pool.global_liquidity > 0, ErrorCode::InsufficientPoolLiquidity);
+    require!(amount_0 > 0 || amount_1 > 0, ErrorCode::InsufficientInputAmount);
+
+    let fee_0 = get_flash_fee(amount_0)?;
+    let fee_1 = get_flash_fee(amount_1)?;
+
+    let balance_0_before = ctx.accounts.pool_token_0.amount;
+    let balance_1_before = ctx.accounts.pool_token_1.amount;
+
+    pool.lock()?;
+    pool.exit(&crate::ID)?;
+
+    if amount_0 > 0 {
+        transfer_from_vault_to_user(
+            pool,
+            &ctx.accounts.pool_token_0,
+            &ctx.accounts.recipient_token_0,
+            &ctx.accounts.token_mint_0,
+            &ctx.accounts.token_program_0,
+            ctx.remaining_accounts,
+            amount_0,
+        )?;
+    }
+
+    if amount_1 > 0 {
+        transfer_from_vault_to_user(
+            pool,
+            &ctx.accounts.pool_token_1,
+            &ctx.accounts.recipient_token_1,
+            &ctx.accounts.token_mint_1,
+            &ctx.accounts.token_program_1,
+            ctx.remaining_accounts,
+            amount_1,
+        )?;
+    }
+
+    let callback = Instruction {
+        program_id: ctx.accounts.callback_program.key(),
+        accounts: ctx
+            .remaining_accounts
+            .iter()
+            .map(|account| AccountMeta {
+                pubkey: account.key(),
+                is_signer: account.is_signer,
+                is_writable: account.is_writable,
+            })
+            .collect(),
+        data: flash_callback_data(fee_0, fee_1, data)?,
+    };
+    let mut callback_accounts = ctx.remaining_accounts.to_vec();
+    callback_accounts.push(ctx.accounts.callback_program.to_account_info());
+    invoke(&callback, &callback_accounts)?;
+
+    ctx.accounts.pool_token_0.reload()?;
+    ctx.accounts.pool_token_1.reload()?;
+    let balance_0_after = ctx.accounts.pool_token_0.amount;
+    let balance_1_after = ctx.accounts.pool_token_1.amount;
+
+    let repaid_0 = balance_0_before
+        .checked_add(fee_0)
+        .ok_or(ErrorCode::ArithmeticOverflow)?;
+    let repaid_1 = balance_1_before
+        .checked_add(fee_1)
+        .ok_or(ErrorCode::ArithmeticOverflow)?;
+    require!(
+        balance_0_after >= repaid_0 && balance_1_after >= repaid_1,
+        ErrorCode::FlashLoanNotRepaid
+    );
+
+    // Anything paid above the principal is credited to in-range liquidity.
+    let paid_0 = balance_0_after - balance_0_before;
+    let paid_1 = balance_1_after - balance_1_before;
+
+    let pool = &mut ctx.accounts.pool;
+    pool.credit_fees(paid_0, paid_1)?;
+    pool.unlock();
+
+    emit_cpi!(events::Flash {
+        pool: pool.key(),
+        sender: ctx.accounts.payer.key(),
+        amount_0,
+        amount_1,
+        paid_0,
+        paid_1,
+    });
+
+    Ok(())
+}
@@ -0,0 +1,432 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::{Mint, TokenAccount, TokenInterface};
+use crate::events;
+use crate::states::*;
+use crate::utils::ErrorCode;
+use crate::utils::math::*;
+use crate::utils::token::*;
+
+#[event_cpi]
+#[derive(Accounts)]
+#[instruction(tick: i32)]
+pub struct PlaceLimitOrder<'info> {
+    #[account(
+        mut,
+        has_one = token_mint_0,
+        has_one = token_mint_1,
+    )]
+    pub pool: Account<'info, Pool>,
+
+    /// CHECK: tick array PDA holding `tick`, created if needed and updated
+    /// in place.
+    #[account(
+        mut,
+        seeds = [
+            b"tick_array",
+            pool.key().as_ref(),
+            &TickArray::get_starting_tick_index(tick, pool.tick_spacing).to_le_bytes()
+        ],
+        bump
+    )]
+    pub tick_array: UncheckedAccount<'info>,
+
+    #[account(
+        init_if_needed,
+        payer = owner,
+        space = LimitOrder::SPACE,
+        seeds = [
+            b"limit_order",
+            owner.key().as_ref(),
+            pool.key().as_ref(),
+            &tick.to_le_bytes(),
+        ],
+        bump,
+    )]
+    pub limit_order: Account<'info, LimitOrder>,
+
+    #[account(
+        mut,
+        constraint = user_token_0.mint == pool.token_mint_0 @ ErrorCode::InvalidTokenMint,
+        constraint = user_token_0.owner == owner.key() @ ErrorCode::InvalidTokenAccountOwner,
+    )]
+    pub user_token_0: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        constraint = user_token_1.mint == pool.token_mint_1 @ ErrorCode::InvalidTokenMint,
+        constraint = user_token_1.owner == owner.key() @ ErrorCode::InvalidTokenAccountOwner,
+    )]
+    pub user_token_1: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        address = pool.token_vault_0 @ ErrorCode::InvalidPoolVault,
+    )]
+    pub pool_token_0: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        address = pool.token_vault_1 @ ErrorCode::InvalidPoolVault,
+    )]
+    pub pool_token_1: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    pub token_mint_0: InterfaceAccount<'info, Mint>,
+    pub token_mint_1: InterfaceAccount<'info, Mint>,
+    pub system_program: Program<'info, System>,
+    pub token_program_0: Interface<'info, TokenInterface>,
+    pub token_program_1: Interface<'info, TokenInterface>,
+}
+
+/// Rests `amount` on `tick`. Orders above the current price sell token 0 and
+/// orders below it sell token 1; the swap that moves the price through the
+/// tick fills them, buying them at the tick's price if its input covers all
+/// of them. An order filled before must be claimed before the owner places
+/// another on the same tick.
+pub fn place_limit_order<'info>(
+    ctx: Context<'_, '_, '_, 'info, PlaceLimitOrder<'info>>,
+    tick: i32,
+    amount: u64,
+) -> Result<()> {
+    let pool = &mut ctx.accounts.pool;
+    pool.require_not_paused(PAUSE_DEPOSIT)?;
+
+    require!(
+        tick % pool.tick_spacing == 0 && (MIN_TICK..=MAX_TICK).contains(&tick),
+        ErrorCode::InvalidTickRange
+    );
+    require!(tick != pool.current_tick, ErrorCode::InvalidTickRange);
+    let zero_for_one = tick > pool.current_tick;
+
+    let (user_token, pool_token, mint, token_program) = if zero_for_one {
+        (
+            &ctx.accounts.user_token_0,
+            &ctx.accounts.pool_token_0,
+            &ctx.accounts.token_mint_0,
+            &ctx.accounts.token_program_0,
+        )
+    } else {
+        (
+            &ctx.accounts.user_token_1,
+            &ctx.accounts.pool_token_1,
+            &ctx.accounts.token_mint_1,
+            &ctx.accounts.token_program_1,
+        )
+    };
+
+    // Only what reaches the vault after transfer fees is credited to the order.
+    let amount_received = amount
+        .checked_sub(get_transfer_fee(mint, amount)?)
+        .ok_or(ErrorCode::ArithmeticOverflow)?;
+    require!(amount_received > 0, ErrorCode::InsufficientInputAmount);
+
+    TickArray::init_if_needed(
+        &ctx.accounts.owner,
+        &ctx.accounts.system_program,
+        &ctx.accounts.tick_array,
+        pool.key(),
+        tick,
+        pool.tick_spacing,
+    )?;
+    let mut tick_array = TickArray::load(&ctx.accounts.tick_array)?;
+    let tick_info = tick_array.get_tick_info_initialized(pool, tick)?;
+    if tick_info.limit_order_amount == 0 {
+        tick_info.limit_order_zero_for_one = zero_for_one;
+    }
+    require!(
+        tick_info.limit_order_zero_for_one == zero_for_one,
+        ErrorCode::InvalidLimitOrderDirection
+    );
+    tick_info.limit_order_amount = tick_info
+        .limit_order_amount
+        .checked_add(amount_received)
+        .ok_or(ErrorCode::ArithmeticOverflow)?;
+    let epoch = tick_info.limit_order_epoch;
+    tick_array.store(&ctx.accounts.tick_array)?;
+
+    let limit_order = &mut ctx.accounts.limit_order;
+    if limit_order.owner == Pubkey::default() {
+        limit_order.owner = ctx.accounts.owner.key();
+        limit_order.pool = pool.key();
+        limit_order.tick = tick;
+        limit_order.zero_for_one = zero_for_one;
+        limit_order.epoch = epoch;
+        limit_order.bump = ctx.bumps.limit_order;
+    }
+    require!(limit_order.epoch == epoch, ErrorCode::LimitOrderFilled);
+    limit_order.amount_in = limit_order
+        .amount_in
+        .checked_add(amount_received)
+        .ok_or(ErrorCode::ArithmeticOverflow)?;
+
+    pool.lock()?;
+    pool.exit(&crate::ID)?;
+
+    transfer_from_user_to_vault(
+        &ctx.accounts.owner,
+        user_token,
+        pool_token,
+        mint,
+        token_program,
+        ctx.remaining_accounts,
+        amount,
+    )?;
+
+    pool.unlock();
+
+    emit_cpi!(events::LimitOrderPlaced {
+        pool: pool.key(),
+        limit_order: limit_order.key(),
+        owner: limit_order.owner,
+        tick,
+        zero_for_one,
+        epoch: limit_order.epoch,
+        amount_in: amount_received,
+    });
+
+    Ok(())
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct ClaimFilledOrder<'info> {
+    #[account(
+        mut,
+        has_one = token_mint_0,
+        has_one = token_mint_1,
+    )]
+    pub pool: Account<'info, Pool>,
+
+    #[account(
+        seeds = [
+            b"tick_array",
+            pool.key().as_ref(),
+            &TickArray::get_starting_tick_index(limit_order.tick, pool.tick_spacing).to_le_bytes()
+        ],
+        bump = tick_array.bump,
+    )]
+    pub tick_array: Account<'info, TickArray>,
+
+    #[account(
+        mut,
+        close = owner,
+        has_one = owner,
+        has_one = pool,
+    )]
+    pub limit_order: Account<'info, LimitOrder>,
+
+    #[account(
+        mut,
+        constraint = user_token_0.mint == pool.token_mint_0 @ ErrorCode::InvalidTokenMint,
+        constraint = user_token_0.owner == owner.key() @ ErrorCode::InvalidTokenAccountOwner,
+    )]
+    pub user_token_0: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        constraint = user_token_1.mint == pool.token_mint_1 @ ErrorCode::InvalidTokenMint,
+        constraint = user_token_1.owner == owner.key() @ ErrorCode::InvalidTokenAccountOwner,
+    )]
+    pub user_token_1: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        address = pool.token_vault_0 @ ErrorCode::InvalidPoolVault,
+    )]
+    pub pool_token_0: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        address = pool.token_vault_1 @ ErrorCode::InvalidPoolVault,
+    )]
+    pub pool_token_1: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    pub token_mint_0: InterfaceAccount<'info, Mint>,
+    pub token_mint_1: InterfaceAccount<'info, Mint>,
+    pub token_program_0: Interface<'info, TokenInterface>,
+    pub token_program_1: Interface<'info, TokenInterface>,
+}
+
+/// Pays out a filled order in the token it was bought into and closes it.
+pub fn claim_filled_order<'info>(
+    ctx: Context<'_, '_, '_, 'info, ClaimFilledOrder<'info>>,
+) -> Result<u64> {
+    let pool = &mut ctx.accounts.pool;
+    pool.require_not_paused(PAUSE_WITHDRAW)?;
+
+    let limit_order = &ctx.accounts.limit_order;
+    let tick_info = ctx
+        .accounts
+        .tick_array
+        .get_tick_info(limit_order.tick, pool.tick_spacing)?;
+    require!(
+        limit_order.epoch < tick_info.limit_order_epoch,
+        ErrorCode::LimitOrderNotFilled
+    );
+
+    let amount_out = get_amount_out_at_sqrt_price(
+        limit_order.amount_in,
+        get_sqrt_price_from_tick(limit_order.tick)?,
+        limit_order.zero_for_one,
+    )?;
+
+    pool.lock()?;
+    pool.exit(&crate::ID)?;
+
+    if amount_out > 0 {
+        if limit_order.zero_for_one {
+            transfer_from_vault_to_user(
+                pool,
+                &ctx.accounts.pool_token_1,
+                &ctx.accounts.user_token_1,
+                &ctx.accounts.token_mint_1,
+                &ctx.accounts.token_program_1,
+                ctx.remaining_accounts,
+                amount_out,
+            )?;
+        } else {
+            transfer_from_vault_to_user(
+                pool,
+                &ctx.accounts.pool_token_0,
+                &ctx.accounts.user_token_0,
+                &ctx.accounts.token_mint_0,
+                &ctx.accounts.token_program_0,
+                ctx.remaining_accounts,
+                amount_out,
+            )?;
+        }
+    }
+
+    pool.unlock();
+
+    emit_cpi!(events::LimitOrderClaimed {
+        pool: pool.key(),
+        limit_order: limit_order.key(),
+        owner: limit_order.owner,
+        tick: limit_order.tick,
+        amount_in: limit_order.amount_in,
+        amount_out,
+    });
+
+    Ok(amount_out)
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct CancelLimitOrder<'info> {
+    #[account(
+        mut,
+        has_one = token_mint_0,
+        has_one = token_mint_1,
+    )]
+    pub pool: Account<'info, Pool>,
+
+    #[account(
+        mut,
+        seeds = [
+            b"tick_array",
+            pool.key().as_ref(),
+            &TickArray::get_starting_tick_index(limit_order.tick, pool.tick_spacing).to_le_bytes()
+        ],
+        bump = tick_array.bump,
+    )]
+    pub tick_array: Account<'info, TickArray>,
+
+    #[account(
+        mut,
+        close = owner,
+        has_one = owner,
+        has_one = pool,
+    )]
+    pub limit_order: Account<'info, LimitOrder>,
+
+    #[account(
+        mut,
+        constraint = user_token_0.mint == pool.token_mint_0 @ ErrorCode::InvalidTokenMint,
+        constraint = user_token_0.owner == owner.key() @ ErrorCode::InvalidTokenAccountOwner,
+    )]
+    pub user_token_0: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        constraint = user_token_1.mint == pool.token_mint_1 @ ErrorCode::InvalidTokenMint,
+        constraint = user_token_1.owner == owner.key() @ ErrorCode::InvalidTokenAccountOwner,
+    )]
+    pub user_token_1: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        address = pool.token_vault_0 @ ErrorCode::InvalidPoolVault,
+    )]
+    pub pool_token_0: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        address = pool.token_vault_1 @ ErrorCode::InvalidPoolVault,
+    )]
+    pub pool_token_1: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    pub token_mint_0: InterfaceAccount<'info, Mint>,
+    pub token_mint_1: InterfaceAccount<'info, Mint>,
+    pub token_program_0: Interface<'info, TokenInterface>,
+    pub token_program_1: Interface<'info, TokenInterface>,
+}
+
+/// Takes an unfilled order off its tick, refunds it in the token it was
+/// selling and closes it. Returns the amount refunded.
+pub fn cancel_limit_order<'info>(
+    ctx: Context<'_, '_, '_, 'info, CancelLimitOrder<'info>>,
+) -> Result<u64> {
+    let pool = &mut ctx.accounts.pool;
+    pool.require_not_paused(PAUSE_WITHDRAW)?;
+
+    let limit_order = &ctx.accounts.limit_order;
+    let tick_info = ctx
+        .accounts
+        .tick_array
+        .get_tick_info_mutable(limit_order.tick, pool.tick_spacing)?;
+    require!(
+        limit_order.epoch == tick_info.limit_order_epoch,
+        ErrorCode::LimitOrderFilled
+    );
+    tick_info.limit_order_amount = tick_info
+        .limit_order_amount
+        .checked_sub(limit_order.amount_in)
+        .ok_or(ErrorCode::ArithmeticOverflow)?;
+
+    pool.lock()?;
+    pool.exit(&crate::ID)?;
+
+    if limit_order.zero_for_one {
+        transfer_from_vault_to_user(
+            pool,
+            &ctx.accounts.pool_token_0,
+            &ctx.accounts.user_token_0,
+            &ctx.accounts.token_mint_0,
+            &ctx.accounts.token_program_0,
+            ctx.remaining_accounts,
+            limit_order.amount_in,
+        )?;
+    } else {
+        transfer_from_vault_to_user(
+            pool,
+            &ctx.accounts.pool_token_1,
+            &ctx.accounts.user_token_1,
+            &ctx.accounts.token_mint_1,
+            &ctx.accounts.token_program_1,
+            ctx.remaining_accounts,
+            limit_order.amount_in,
+        )?;
+    }
+
+    pool.unlock();
+
+    emit_cpi!(events::LimitOrderCancelled {
+        pool: pool.key(),
+        limit_order: limit_order.key(),
+        owner: limit_order.owner,
+        tick: limit_order.tick,
+        amount_in: limit_order.amount_in,
+    });
+
+    Ok(limit_order.amount_in)
+}
@@ -0,0 +1,39 @@
+use anchor_lang::prelude::*;
+use crate::events;
+use crate::states::*;
+use crate::utils::ErrorCode;
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct LockPosition<'info> {
+    pub owner: Signer<'info>,
+
+    pub pool: Account<'info, Pool>,
+
+    #[account(
+        mut,
+        has_one = owner @ ErrorCode::InvalidPositionOwner,
+        has_one = pool @ ErrorCode::InvalidPositionRange,
+    )]
+    pub position: Account<'info, Position>,
+}
+
+/// Locks the position's liquidity until `lock_until`. An existing lock can
+/// only be pushed further out, never shortened or removed.
+pub fn lock_position(ctx: Context<LockPosition>, lock_until: i64) -> Result<()> {
+    let position = &mut ctx.accounts.position;
+    require!(
+        lock_until > position.lock_until && lock_until > Clock::get()?.unix_timestamp,
+        ErrorCode::InvalidLockUntil
+    );
+    position.lock_until = lock_until;
+
+    emit_cpi!(events::PositionLocked {
+        pool: position.pool,
+        position: position.key(),
+        owner: position.owner,
+        lock_until,
+    });
+
+    Ok(())
+}
@@ -0,0 +1,93 @@
+use anchor_lang::prelude::*;
+use crate::events;
+use crate::states::*;
+use crate::utils::ErrorCode;
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct MergePositions<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    /// Owner of the destination position, agreeing to take the liquidity.
+    /// Positions are keyed by owner and range, so it is never `owner`.
+    pub destination_owner: Signer<'info>,
+
+    pub pool: Account<'info, Pool>,
+
+    #[account(
+        mut,
+        close = owner,
+        has_one = owner @ ErrorCode::InvalidPositionOwner,
+        has_one = pool @ ErrorCode::InvalidPositionRange,
+    )]
+    pub source_position: Account<'info, Position>,
+
+    #[account(
+        mut,
+        constraint = destination_position.owner == destination_owner.key() @ ErrorCode::InvalidPositionOwner,
+        constraint = destination_position.key() != source_position.key() @ ErrorCode::InvalidPositionRange,
+        constraint = destination_position.pool == source_position.pool @ ErrorCode::InvalidPositionRange,
+        constraint = destination_position.tick_lower == source_position.tick_lower
+            && destination_position.tick_upper == source_position.tick_upper
+            @ ErrorCode::InvalidPositionRange,
+    )]
+    pub destination_position: Account<'info, Position>,
+
+    #[account(
+        seeds = [
+            b"tick_array",
+            pool.key().as_ref(),
+            &TickArray::get_starting_tick_index(source_position.tick_lower, pool.tick_spacing).to_le_bytes()
+        ],
+        bump = lower_tick_array.bump,
+    )]
+    pub lower_tick_array: Account<'info, TickArray>,
+
+    #[account(
+        seeds = [
+            b"tick_array",
+            pool.key().as_ref(),
+            &TickArray::get_starting_tick_index(source_position.tick_upper, pool.tick_spacing).to_le_bytes()
+        ],
+        bump = upper_tick_array.bump,
+    )]
+    pub upper_tick_array: Account<'info, TickArray>,
+}
+
+/// Folds all liquidity of the signer's `source_position` into a same-range
+/// `destination_position`, whose owner co-signs, and closes the source, along
+/// with its accrued fees. Tick state is untouched.
+pub fn merge_positions(ctx: Context<MergePositions>) -> Result<()> {
+    let source_position = &mut ctx.accounts.source_position;
+    let destination_position = &mut ctx.accounts.destination_position;
+
+    destination_position.require_can_absorb(source_position, Clock::get()?.unix_timestamp)?;
+
+    let (fee_growth_inside_0_x64, fee_growth_inside_1_x64) = TickArray::get_fee_growth_inside(
+        &ctx.accounts.lower_tick_array,
+        &ctx.accounts.upper_tick_array,
+        &ctx.accounts.pool,
+        source_position.tick_lower,
+        source_position.tick_upper,
+    )?;
+    source_position.accrue_fees(fee_growth_inside_0_x64, fee_growth_inside_1_x64)?;
+    destination_position.accrue_fees(fee_growth_inside_0_x64, fee_growth_inside_1_x64)?;
+    destination_position.absorb_fees(source_position)?;
+
+    let liquidity = source_position.liquidity;
+    destination_position.liquidity = destination_position
+        .liquidity
+        .checked_add(liquidity)
+        .ok_or(ErrorCode::ArithmeticOverflow)?;
+    source_position.liquidity = 0;
+
+    emit_cpi!(events::PositionsMerged {
+        pool: destination_position.pool,
+        source_position: source_position.key(),
+        destination_position: destination_position.key(),
+        liquidity,
+    });
+
+    Ok(())
+}
@@ -5,6 +5,25 @@ pub mod burn;
 pub mod increase_liquidity;
 pub mod decrease_liquidity;
 pub mod close_positon;
+pub mod observe;
+pub mod config;
+pub mod flash;
+pub mod swap_route;
+pub mod quote_swap;
+pub mod limit_order;
+pub mod rebalance_position;
+pub mod lock_position;
+pub mod set_position_operator;
+pub mod transfer_position;
+pub mod split_position;
+pub mod merge_positions;
+pub mod position_bundle;
+pub mod batch_modify_liquidity;
+pub mod zap_in;
+pub mod zap_out;
+pub mod compound_position;
+pub mod collect_fees;
+pub mod set_position_keeper;
 
 pub use pool::*;
 pub use open_position::*;
@@ -12,4 +31,23 @@ pub use swap::*;
 pub use burn::*;
 pub use increase_liquidity::*;
 pub use decrease_liquidity::*;
-pub use close_positon::*;
\ No newline at end of file
+pub use close_positon::*;
+pub use observe::*;
+pub use config::*;
+pub use flash::*;
+pub use swap_route::*;
+pub use quote_swap::*;
+pub use limit_order::*;
+pub use rebalance_position::*;
+pub use lock_position::*;
+pub use set_position_operator::*;
+pub use transfer_position::*;
+pub use split_position::*;
+pub use merge_positions::*;
+pub use position_bundle::*;
+pub use batch_modify_liquidity::*;
+pub use zap_in::*;
+pub use zap_out::*;
+pub use compound_position::*;
+pub use collect_fees::*;
+pub use set_position_keeper::*;
\ No newline at end of file
@@ -0,0 +1,33 @@
+use anchor_lang::prelude::*;
+use crate::states::*;
+
+#[derive(Accounts)]
+pub struct Observe<'info> {
+    pub pool: Account<'info, Pool>,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct ObserveResult {
+    pub tick_cumulatives: Vec<i64>,
+    pub seconds_per_liquidity_cumulative_x64s: Vec<u128>,
+}
+
+pub fn observe(ctx: Context<Observe>, seconds_agos: Vec<u32>) -> Result<ObserveResult> {
+    let pool = &ctx.accounts.pool;
+    let now = Clock::get()?.unix_timestamp as u32;
+
+    let mut tick_cumulatives = Vec::with_capacity(seconds_agos.len());
+    let mut seconds_per_liquidity_cumulative_x64s = Vec::with_capacity(seconds_agos.len());
+
+    for seconds_ago in seconds_agos {
+        let (tick_cumulative, seconds_per_liquidity_cumulative_x64) =
+            pool.observe_single(now, seconds_ago)?;
+        tick_cumulatives.push(tick_cumulative);
+        seconds_per_liquidity_cumulative_x64s.push(seconds_per_liquidity_cumulative_x64);
+    }
+
+    Ok(ObserveResult {
+        tick_cumulatives,
+        seconds_per_liquidity_cumulative_x64s,
+    })
+}
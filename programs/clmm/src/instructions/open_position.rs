@@ -1,10 +1,12 @@
 use anchor_lang::prelude::*;
-use anchor_spl::token::{self, Transfer};
-use anchor_spl::token::{Mint, Token, TokenAccount};
+use anchor_spl::token_interface::{Mint, TokenAccount, TokenInterface};
+use crate::events;
 use crate::states::*;
 use crate::utils::ErrorCode;
 use crate::utils::math::*;
+use crate::utils::token::*;
 
+#[event_cpi]
 #[derive(Accounts)]
 #[instruction(owner: Pubkey, lower_tick: i32, upper_tick: i32, liquidity_amount: u128, tick_array_lower_start_index: i32, tick_array_upper_start_index: i32)]
 pub struct OpenPosition<'info> {
@@ -15,10 +17,10 @@ pub struct OpenPosition<'info> {
     )]
     pub pool: Account<'info, Pool>,
 
+    /// CHECK: tick array PDA holding `lower_tick`, created if needed and
+    /// updated in place since both arrays may be the same account.
     #[account(
-        init_if_needed,
-        payer = payer,
-        space = TickArray::SPACE,
+        mut,
         seeds = [
             b"tick_array",
             pool.key().as_ref(),
@@ -26,12 +28,12 @@ pub struct OpenPosition<'info> {
         ],
         bump
     )]
-    pub lower_tick_array: Account<'info, TickArray>,
+    pub lower_tick_array: UncheckedAccount<'info>,
 
+    /// CHECK: tick array PDA holding `upper_tick`, created if needed and
+    /// updated in place since both arrays may be the same account.
     #[account(
-        init_if_needed,
-        payer = payer,
-        space = TickArray::SPACE,
+        mut,
         seeds = [
             b"tick_array",
             pool.key().as_ref(),
@@ -39,7 +41,7 @@ pub struct OpenPosition<'info> {
         ],
         bump
     )]
-    pub upper_tick_array: Account<'info, TickArray>,
+    pub upper_tick_array: UncheckedAccount<'info>,
 
     #[account(
         init_if_needed,
@@ -56,60 +58,92 @@ pub struct OpenPosition<'info> {
     )]
     pub position: Account<'info, Position>,
 
-    #[account(mut)]
-    pub user_token_0: Account<'info, TokenAccount>,
-    #[account(mut)]
-    pub user_token_1: Account<'info, TokenAccount>,
-    #[account(mut)]
-    pub pool_token_0: Account<'info, TokenAccount>,
-    #[account(mut)]
-    pub pool_token_1: Account<'info, TokenAccount>,
+    #[account(
+        mut,
+        constraint = user_token_0.mint == pool.token_mint_0 @ ErrorCode::InvalidTokenMint,
+        constraint = user_token_0.owner == payer.key() @ ErrorCode::InvalidTokenAccountOwner,
+    )]
+    pub user_token_0: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        constraint = user_token_1.mint == pool.token_mint_1 @ ErrorCode::InvalidTokenMint,
+        constraint = user_token_1.owner == payer.key() @ ErrorCode::InvalidTokenAccountOwner,
+    )]
+    pub user_token_1: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        address = pool.token_vault_0 @ ErrorCode::InvalidPoolVault,
+    )]
+    pub pool_token_0: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        address = pool.token_vault_1 @ ErrorCode::InvalidPoolVault,
+    )]
+    pub pool_token_1: InterfaceAccount<'info, TokenAccount>,
     #[account(mut)]
     pub payer: Signer<'info>,
     
-    pub token_mint_0: Account<'info, Mint>,
-    pub token_mint_1: Account<'info, Mint>,
+    pub token_mint_0: InterfaceAccount<'info, Mint>,
+    pub token_mint_1: InterfaceAccount<'info, Mint>,
     pub system_program: Program<'info, System>,
-    pub token_program: Program<'info, Token>,
+    pub token_program_0: Interface<'info, TokenInterface>,
+    pub token_program_1: Interface<'info, TokenInterface>,
     pub rent: Sysvar<'info, Rent>,
 }
 
 
-pub fn open_position(
-    ctx: Context<OpenPosition>,
+pub fn open_position<'info>(
+    ctx: Context<'_, '_, '_, 'info, OpenPosition<'info>>,
     owner: Pubkey,
     lower_tick: i32,
     upper_tick: i32,
     liquidity_amount: u128,
     _tick_array_lower_start_index: i32, // Added
-    _tick_array_upper_start_index: i32  // Added
+    _tick_array_upper_start_index: i32, // Added
+    native_sol: bool,
+    lock_until: i64,
 ) -> Result<(u64, u64)> {
     let pool = &mut ctx.accounts.pool;
+    pool.require_not_paused(PAUSE_DEPOSIT)?;
     let position = &mut ctx.accounts.position;
 
     require!(liquidity_amount > 0, ErrorCode::InsufficientInputAmount);
-    
-
-    let lower_tick_array = &mut ctx.accounts.lower_tick_array;
-    let upper_tick_array = &mut ctx.accounts.upper_tick_array;
-
-
-    if lower_tick_array.starting_tick == 0 && lower_tick_array.pool == Pubkey::default() {
-        lower_tick_array.pool = pool.key();
-        lower_tick_array.starting_tick = _tick_array_lower_start_index;
-    }
-     if upper_tick_array.starting_tick == 0 && upper_tick_array.pool == Pubkey::default() {
-        upper_tick_array.pool = pool.key();
-        upper_tick_array.starting_tick = _tick_array_upper_start_index;
+    // Only the owner may lock the position, or anyone could freeze it.
+    require!(
+        lock_until == 0 || ctx.accounts.payer.key() == owner,
+        ErrorCode::InvalidPositionOwner
+    );
+    require!(
+        !native_sol || is_native_mint(&pool.token_mint_0) || is_native_mint(&pool.token_mint_1),
+        ErrorCode::NativeMintNotInPool
+    );
+    require!(
+        _tick_array_lower_start_index == TickArray::get_starting_tick_index(lower_tick, pool.tick_spacing)
+            && _tick_array_upper_start_index == TickArray::get_starting_tick_index(upper_tick, pool.tick_spacing),
+        ErrorCode::InvalidTickArrayStartIndex
+    );
+
+    for (tick_array, tick) in [
+        (&ctx.accounts.lower_tick_array, lower_tick),
+        (&ctx.accounts.upper_tick_array, upper_tick),
+    ] {
+        TickArray::init_if_needed(
+            &ctx.accounts.payer,
+            &ctx.accounts.system_program,
+            tick_array,
+            pool.key(),
+            tick,
+            pool.tick_spacing,
+        )?;
     }
-
-    let lower_tick_info =
-        lower_tick_array.get_tick_info_mutable(lower_tick, pool.tick_spacing)?;
-    let upper_tick_info =
-        upper_tick_array.get_tick_info_mutable(upper_tick, pool.tick_spacing)?;
-
-    lower_tick_info.update_liquidity(liquidity_amount as i128, true)?;
-    upper_tick_info.update_liquidity(liquidity_amount as i128, false)?;
+    let (fee_growth_inside_0_x64, fee_growth_inside_1_x64) = TickArray::update_position_ticks_in_place(
+        &ctx.accounts.lower_tick_array,
+        &ctx.accounts.upper_tick_array,
+        pool,
+        lower_tick,
+        upper_tick,
+        liquidity_amount as i128,
+    )?;
 
     let (amount_0, amount_1) = get_amounts_for_liquidity(
         pool.sqrt_price_x96,
@@ -118,6 +152,7 @@ pub fn open_position(
         liquidity_amount,
     )?;
 
+    position.accrue_fees(fee_growth_inside_0_x64, fee_growth_inside_1_x64)?;
     if position.liquidity == 0 && position.owner == Pubkey::default() {
         position.owner = owner;
         position.pool = pool.key();
@@ -125,6 +160,7 @@ pub fn open_position(
         position.tick_upper = upper_tick;
         position.liquidity = liquidity_amount;
         position.bump = ctx.bumps.position;
+        position.lock_until = lock_until;
     } else {
         require!(position.owner == owner, ErrorCode::InvalidPositionOwner);
         require!(
@@ -135,40 +171,80 @@ pub fn open_position(
             .liquidity
             .checked_add(liquidity_amount)
             .ok_or(ErrorCode::ArithmeticOverflow)?;
+        // Topping up may extend an existing lock but never shortens it.
+        position.lock_until = position.lock_until.max(lock_until);
     }
 
-    pool.global_liquidity = pool
-        .global_liquidity
-        .checked_add(liquidity_amount)
+    pool.write_observation(Clock::get()?.unix_timestamp as u32)?;
+    pool.apply_liquidity_delta(lower_tick, upper_tick, liquidity_amount as i128)?;
+
+    // Gross up deposits so the vaults receive the full amounts after transfer fees.
+    let amount_0 = amount_0
+        .checked_add(get_transfer_inverse_fee(&ctx.accounts.token_mint_0, amount_0)?)
+        .ok_or(ErrorCode::ArithmeticOverflow)?;
+    let amount_1 = amount_1
+        .checked_add(get_transfer_inverse_fee(&ctx.accounts.token_mint_1, amount_1)?)
         .ok_or(ErrorCode::ArithmeticOverflow)?;
 
+    pool.lock()?;
+    pool.exit(&crate::ID)?;
+
     if amount_0 > 0 {
-        token::transfer(
-            CpiContext::new(
-                ctx.accounts.token_program.to_account_info(),
-                Transfer {
-                    from: ctx.accounts.user_token_0.to_account_info(),
-                    to: ctx.accounts.pool_token_0.to_account_info(),
-                    authority: ctx.accounts.payer.to_account_info(),
-                },
-            ),
+        if native_sol && is_native_mint(&pool.token_mint_0) {
+            wrap_native(
+                &ctx.accounts.payer,
+                &ctx.accounts.user_token_0,
+                &ctx.accounts.token_program_0,
+                &ctx.accounts.system_program,
+                amount_0,
+            )?;
+        }
+
+        transfer_from_user_to_vault(
+            &ctx.accounts.payer,
+            &ctx.accounts.user_token_0,
+            &ctx.accounts.pool_token_0,
+            &ctx.accounts.token_mint_0,
+            &ctx.accounts.token_program_0,
+            ctx.remaining_accounts,
             amount_0,
         )?;
     }
 
     if amount_1 > 0 {
-        token::transfer(
-            CpiContext::new(
-                ctx.accounts.token_program.to_account_info(),
-                Transfer {
-                    from: ctx.accounts.user_token_1.to_account_info(),
-                    to: ctx.accounts.pool_token_1.to_account_info(),
-                    authority: ctx.accounts.payer.to_account_info(),
-                },
-            ),
+        if native_sol && is_native_mint(&pool.token_mint_1) {
+            wrap_native(
+                &ctx.accounts.payer,
+                &ctx.accounts.user_token_1,
+                &ctx.accounts.token_program_1,
+                &ctx.accounts.system_program,
+                amount_1,
+            )?;
+        }
+
+        transfer_from_user_to_vault(
+            &ctx.accounts.payer,
+            &ctx.accounts.user_token_1,
+            &ctx.accounts.pool_token_1,
+            &ctx.accounts.token_mint_1,
+            &ctx.accounts.token_program_1,
+            ctx.remaining_accounts,
             amount_1,
         )?;
     }
 
+    pool.unlock();
+
+    emit_cpi!(events::PositionOpened {
+        pool: pool.key(),
+        position: position.key(),
+        owner,
+        tick_lower: lower_tick,
+        tick_upper: upper_tick,
+        liquidity: liquidity_amount,
+        amount_0,
+        amount_1,
+    });
+
     Ok((amount_0, amount_1))
 }
@@ -1,9 +1,12 @@
 use anchor_lang::prelude::*;
-use anchor_spl::token::{Mint, Token, TokenAccount};
+use anchor_spl::token_interface::{Mint, TokenAccount, TokenInterface};
+use crate::events;
 use crate::states::*;
 use crate::utils::ErrorCode;
 use crate::utils::math::*;
+use crate::utils::token::*;
 
+#[event_cpi]
 #[derive(Accounts)]
 #[instruction(tick_spacing: i32)]
 pub struct InitializePool<'info> {
@@ -24,27 +27,38 @@ pub struct InitializePool<'info> {
     )]
     pub pool: Account<'info, Pool>,
 
-    pub token_mint_0: Account<'info, Mint>,
-    pub token_mint_1: Account<'info, Mint>,
+    #[account(
+        seeds = [b"config".as_ref()],
+        bump = config.bump,
+    )]
+    pub config: Account<'info, Config>,
+
+    #[account(mint::token_program = token_program_0)]
+    pub token_mint_0: InterfaceAccount<'info, Mint>,
+    #[account(mint::token_program = token_program_1)]
+    pub token_mint_1: InterfaceAccount<'info, Mint>,
 
     #[account(
         init,
         payer = payer,
         token::mint = token_mint_0,
         token::authority = pool,
+        token::token_program = token_program_0,
     )]
-    pub token_vault_0: Account<'info, TokenAccount>,
+    pub token_vault_0: InterfaceAccount<'info, TokenAccount>,
 
     #[account(
         init,
         payer = payer,
         token::mint = token_mint_1,
         token::authority = pool,
+        token::token_program = token_program_1,
     )]
-    pub token_vault_1: Account<'info, TokenAccount>,
+    pub token_vault_1: InterfaceAccount<'info, TokenAccount>,
 
     pub system_program: Program<'info, System>,
-    pub token_program: Program<'info, Token>,
+    pub token_program_0: Interface<'info, TokenInterface>,
+    pub token_program_1: Interface<'info, TokenInterface>,
     pub rent: Sysvar<'info, Rent>,
 }
 
@@ -56,6 +70,16 @@ pub fn init_pool(ctx: Context<InitializePool>, tick_spacing: i32, initial_sqrt_p
         ctx.accounts.token_mint_0.key() != ctx.accounts.token_mint_1.key(),
         ErrorCode::InvalidTokenPair
     );
+    require!(
+        ctx.accounts.token_mint_0.key() < ctx.accounts.token_mint_1.key(),
+        ErrorCode::InvalidMintOrder
+    );
+    require!(
+        (MIN_SQRT_PRICE_X96..MAX_SQRT_PRICE_X96).contains(&initial_sqrt_price),
+        ErrorCode::InvalidPrice
+    );
+    validate_mint_extensions(&ctx.accounts.token_mint_0, &ctx.accounts.config)?;
+    validate_mint_extensions(&ctx.accounts.token_mint_1, &ctx.accounts.config)?;
 
     pool.token_mint_0 = ctx.accounts.token_mint_0.key();
     pool.token_mint_1 = ctx.accounts.token_mint_1.key();
@@ -65,7 +89,104 @@ pub fn init_pool(ctx: Context<InitializePool>, tick_spacing: i32, initial_sqrt_p
     pool.sqrt_price_x96 = initial_sqrt_price;
     pool.current_tick = get_tick_at_sqrt_price(initial_sqrt_price)?;
     pool.tick_spacing = tick_spacing;
+    pool.mint_decimals_0 = ctx.accounts.token_mint_0.decimals;
+    pool.mint_decimals_1 = ctx.accounts.token_mint_1.decimals;
     pool.bump = ctx.bumps.pool;
+    pool.status = POOL_STATUS_UNLOCKED;
+    pool.pause_flags = 0;
+    pool.dynamic_fee_enabled = false;
+    pool.min_fee_rate = DEFAULT_FEE_RATE;
+    pool.max_fee_rate = DEFAULT_FEE_RATE;
+    pool.volatility_accumulator = 0;
+
+    let now = Clock::get()?.unix_timestamp as u32;
+    pool.volatility_updated_at = now;
+    pool.initialize_observations(now);
+
+    emit_cpi!(events::PoolInitialized {
+        pool: pool.key(),
+        token_mint_0: pool.token_mint_0,
+        token_mint_1: pool.token_mint_1,
+        token_vault_0: pool.token_vault_0,
+        token_vault_1: pool.token_vault_1,
+        tick_spacing,
+        sqrt_price_x96: pool.sqrt_price_x96,
+        tick: pool.current_tick,
+    });
+
+    Ok(())
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct SetPauseFlags<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(
+        seeds = [b"config".as_ref()],
+        bump = config.bump,
+        has_one = authority @ ErrorCode::InvalidConfigAuthority,
+    )]
+    pub config: Account<'info, Config>,
+
+    #[account(mut)]
+    pub pool: Account<'info, Pool>,
+}
+
+pub fn set_pause_flags(ctx: Context<SetPauseFlags>, pause_flags: u8) -> Result<()> {
+    require!(pause_flags & !PAUSE_ALL == 0, ErrorCode::InvalidPauseFlags);
+
+    let pool = &mut ctx.accounts.pool;
+    pool.pause_flags = pause_flags;
+
+    emit_cpi!(events::PauseFlagsUpdated {
+        pool: pool.key(),
+        authority: ctx.accounts.authority.key(),
+        pause_flags,
+    });
+
+    Ok(())
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct SetDynamicFee<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(
+        seeds = [b"config".as_ref()],
+        bump = config.bump,
+        has_one = authority @ ErrorCode::InvalidConfigAuthority,
+    )]
+    pub config: Account<'info, Config>,
+
+    #[account(mut)]
+    pub pool: Account<'info, Pool>,
+}
+
+pub fn set_dynamic_fee(
+    ctx: Context<SetDynamicFee>,
+    enabled: bool,
+    min_fee_rate: u32,
+    max_fee_rate: u32,
+) -> Result<()> {
+    require!(
+        min_fee_rate <= max_fee_rate && max_fee_rate <= MAX_FEE_RATE,
+        ErrorCode::InvalidFeeRate
+    );
+
+    let pool = &mut ctx.accounts.pool;
+    pool.dynamic_fee_enabled = enabled;
+    pool.min_fee_rate = min_fee_rate;
+    pool.max_fee_rate = max_fee_rate;
+
+    emit_cpi!(events::DynamicFeeUpdated {
+        pool: pool.key(),
+        authority: ctx.accounts.authority.key(),
+        enabled,
+        min_fee_rate,
+        max_fee_rate,
+    });
 
     Ok(())
-}
\ No newline at end of file
+}
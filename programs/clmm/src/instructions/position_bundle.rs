@@ -0,0 +1,511 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::{Mint, TokenAccount, TokenInterface};
+use crate::events;
+use crate::states::*;
+use crate::utils::ErrorCode;
+use crate::utils::math::*;
+use crate::utils::token::*;
+
+#[derive(Accounts)]
+pub struct InitializePositionBundle<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    pub pool: Account<'info, Pool>,
+
+    #[account(
+        init,
+        payer = owner,
+        space = PositionBundle::SPACE,
+        seeds = [
+            b"position_bundle",
+            owner.key().as_ref(),
+            pool.key().as_ref(),
+        ],
+        bump,
+    )]
+    pub position_bundle: Account<'info, PositionBundle>,
+
+    pub system_program: Program<'info, System>,
+}
+
+pub fn initialize_position_bundle(ctx: Context<InitializePositionBundle>) -> Result<()> {
+    let position_bundle = &mut ctx.accounts.position_bundle;
+    position_bundle.owner = ctx.accounts.owner.key();
+    position_bundle.pool = ctx.accounts.pool.key();
+    position_bundle.bitmap = 0;
+    position_bundle.bump = ctx.bumps.position_bundle;
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct ClosePositionBundle<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    #[account(
+        mut,
+        close = owner,
+        has_one = owner @ ErrorCode::InvalidPositionOwner,
+        constraint = position_bundle.bitmap == 0 @ ErrorCode::PositionBundleNotEmpty,
+    )]
+    pub position_bundle: Account<'info, PositionBundle>,
+}
+
+pub fn close_position_bundle(_ctx: Context<ClosePositionBundle>) -> Result<()> {
+    Ok(())
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+#[instruction(slot: u8, lower_tick: i32, upper_tick: i32)]
+pub struct OpenBundledPosition<'info> {
+    #[account(
+        mut,
+        has_one = token_mint_0,
+        has_one = token_mint_1,
+    )]
+    pub pool: Account<'info, Pool>,
+
+    /// CHECK: tick array PDA holding `lower_tick`, created if needed and
+    /// updated in place since both arrays may be the same account.
+    #[account(
+        mut,
+        seeds = [
+            b"tick_array",
+            pool.key().as_ref(),
+            &TickArray::get_starting_tick_index(lower_tick, pool.tick_spacing).to_le_bytes()
+        ],
+        bump
+    )]
+    pub lower_tick_array: UncheckedAccount<'info>,
+
+    /// CHECK: tick array PDA holding `upper_tick`, created if needed and
+    /// updated in place since both arrays may be the same account.
+    #[account(
+        mut,
+        seeds = [
+            b"tick_array",
+            pool.key().as_ref(),
+            &TickArray::get_starting_tick_index(upper_tick, pool.tick_spacing).to_le_bytes()
+        ],
+        bump
+    )]
+    pub upper_tick_array: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        seeds = [
+            b"position_bundle",
+            owner.key().as_ref(),
+            pool.key().as_ref(),
+        ],
+        bump = position_bundle.bump,
+        has_one = owner @ ErrorCode::InvalidPositionOwner,
+    )]
+    pub position_bundle: Account<'info, PositionBundle>,
+
+    #[account(
+        mut,
+        constraint = user_token_0.mint == pool.token_mint_0 @ ErrorCode::InvalidTokenMint,
+        constraint = user_token_0.owner == owner.key() @ ErrorCode::InvalidTokenAccountOwner,
+    )]
+    pub user_token_0: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        constraint = user_token_1.mint == pool.token_mint_1 @ ErrorCode::InvalidTokenMint,
+        constraint = user_token_1.owner == owner.key() @ ErrorCode::InvalidTokenAccountOwner,
+    )]
+    pub user_token_1: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        address = pool.token_vault_0 @ ErrorCode::InvalidPoolVault,
+    )]
+    pub pool_token_0: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        address = pool.token_vault_1 @ ErrorCode::InvalidPoolVault,
+    )]
+    pub pool_token_1: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    pub token_mint_0: InterfaceAccount<'info, Mint>,
+    pub token_mint_1: InterfaceAccount<'info, Mint>,
+    pub system_program: Program<'info, System>,
+    pub token_program_0: Interface<'info, TokenInterface>,
+    pub token_program_1: Interface<'info, TokenInterface>,
+}
+
+pub fn open_bundled_position<'info>(
+    ctx: Context<'_, '_, '_, 'info, OpenBundledPosition<'info>>,
+    slot: u8,
+    lower_tick: i32,
+    upper_tick: i32,
+    liquidity_amount: u128,
+) -> Result<(u64, u64)> {
+    let pool = &mut ctx.accounts.pool;
+    pool.require_not_paused(PAUSE_DEPOSIT)?;
+
+    require!(
+        lower_tick < upper_tick
+            && lower_tick >= MIN_TICK
+            && upper_tick <= MAX_TICK
+            && lower_tick % pool.tick_spacing == 0
+            && upper_tick % pool.tick_spacing == 0,
+        ErrorCode::InvalidTickRange
+    );
+    require!(liquidity_amount > 0, ErrorCode::InsufficientInputAmount);
+
+    for (tick_array, tick) in [
+        (&ctx.accounts.lower_tick_array, lower_tick),
+        (&ctx.accounts.upper_tick_array, upper_tick),
+    ] {
+        TickArray::init_if_needed(
+            &ctx.accounts.owner,
+            &ctx.accounts.system_program,
+            tick_array,
+            pool.key(),
+            tick,
+            pool.tick_spacing,
+        )?;
+    }
+    let (fee_growth_inside_0_x64, fee_growth_inside_1_x64) = TickArray::update_position_ticks_in_place(
+        &ctx.accounts.lower_tick_array,
+        &ctx.accounts.upper_tick_array,
+        pool,
+        lower_tick,
+        upper_tick,
+        liquidity_amount as i128,
+    )?;
+
+    let position_bundle = &mut ctx.accounts.position_bundle;
+    position_bundle.open_slot(slot, lower_tick, upper_tick)?;
+
+    let position = position_bundle.get_slot_mut(slot, lower_tick, upper_tick)?;
+    position.accrue_fees(fee_growth_inside_0_x64, fee_growth_inside_1_x64)?;
+    let (amount_0, amount_1) = update_bundled_liquidity(pool, position, liquidity_amount as i128)?;
+    let (amount_0, amount_1) = deposit(
+        pool,
+        &ctx.accounts.owner,
+        [&ctx.accounts.user_token_0, &ctx.accounts.user_token_1],
+        [&ctx.accounts.pool_token_0, &ctx.accounts.pool_token_1],
+        [&ctx.accounts.token_mint_0, &ctx.accounts.token_mint_1],
+        [&ctx.accounts.token_program_0, &ctx.accounts.token_program_1],
+        ctx.remaining_accounts,
+        amount_0,
+        amount_1,
+    )?;
+
+    emit_cpi!(events::BundledLiquidityChanged {
+        pool: pool.key(),
+        position_bundle: position_bundle.key(),
+        slot,
+        tick_lower: lower_tick,
+        tick_upper: upper_tick,
+        liquidity_delta: liquidity_amount as i128,
+        amount_0,
+        amount_1,
+    });
+
+    Ok((amount_0, amount_1))
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+#[instruction(slot: u8, lower_tick: i32, upper_tick: i32)]
+pub struct ModifyBundledPosition<'info> {
+    #[account(
+        mut,
+        has_one = token_mint_0,
+        has_one = token_mint_1,
+    )]
+    pub pool: Account<'info, Pool>,
+
+    #[account(
+        mut,
+        seeds = [
+            b"tick_array",
+            pool.key().as_ref(),
+            &TickArray::get_starting_tick_index(lower_tick, pool.tick_spacing).to_le_bytes()
+        ],
+        bump = lower_tick_array.bump,
+        constraint = lower_tick_array.pool == pool.key() @ ErrorCode::InvalidTickArrayPool,
+    )]
+    pub lower_tick_array: Account<'info, TickArray>,
+
+    #[account(
+        mut,
+        seeds = [
+            b"tick_array",
+            pool.key().as_ref(),
+            &TickArray::get_starting_tick_index(upper_tick, pool.tick_spacing).to_le_bytes()
+        ],
+        bump = upper_tick_array.bump,
+        constraint = upper_tick_array.pool == pool.key() @ ErrorCode::InvalidTickArrayPool,
+    )]
+    pub upper_tick_array: Account<'info, TickArray>,
+
+    #[account(
+        mut,
+        seeds = [
+            b"position_bundle",
+            owner.key().as_ref(),
+            pool.key().as_ref(),
+        ],
+        bump = position_bundle.bump,
+        has_one = owner @ ErrorCode::InvalidPositionOwner,
+    )]
+    pub position_bundle: Account<'info, PositionBundle>,
+
+    #[account(
+        mut,
+        constraint = user_token_0.mint == pool.token_mint_0 @ ErrorCode::InvalidTokenMint,
+        constraint = user_token_0.owner == owner.key() @ ErrorCode::InvalidTokenAccountOwner,
+    )]
+    pub user_token_0: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        constraint = user_token_1.mint == pool.token_mint_1 @ ErrorCode::InvalidTokenMint,
+        constraint = user_token_1.owner == owner.key() @ ErrorCode::InvalidTokenAccountOwner,
+    )]
+    pub user_token_1: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        address = pool.token_vault_0 @ ErrorCode::InvalidPoolVault,
+    )]
+    pub pool_token_0: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        address = pool.token_vault_1 @ ErrorCode::InvalidPoolVault,
+    )]
+    pub pool_token_1: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    pub token_mint_0: InterfaceAccount<'info, Mint>,
+    pub token_mint_1: InterfaceAccount<'info, Mint>,
+    pub token_program_0: Interface<'info, TokenInterface>,
+    pub token_program_1: Interface<'info, TokenInterface>,
+}
+
+pub fn increase_bundled_position<'info>(
+    ctx: Context<'_, '_, '_, 'info, ModifyBundledPosition<'info>>,
+    slot: u8,
+    lower_tick: i32,
+    upper_tick: i32,
+    liquidity_amount: u128,
+) -> Result<(u64, u64)> {
+    let pool = &mut ctx.accounts.pool;
+    pool.require_not_paused(PAUSE_DEPOSIT)?;
+    require!(liquidity_amount > 0, ErrorCode::InsufficientInputAmount);
+
+    let (fee_growth_inside_0_x64, fee_growth_inside_1_x64) = TickArray::update_position_ticks(
+        &mut ctx.accounts.lower_tick_array,
+        &mut ctx.accounts.upper_tick_array,
+        pool,
+        lower_tick,
+        upper_tick,
+        liquidity_amount as i128,
+    )?;
+
+    let position_bundle = &mut ctx.accounts.position_bundle;
+    let position = position_bundle.get_slot_mut(slot, lower_tick, upper_tick)?;
+    position.accrue_fees(fee_growth_inside_0_x64, fee_growth_inside_1_x64)?;
+    let (amount_0, amount_1) = update_bundled_liquidity(pool, position, liquidity_amount as i128)?;
+    let (amount_0, amount_1) = deposit(
+        pool,
+        &ctx.accounts.owner,
+        [&ctx.accounts.user_token_0, &ctx.accounts.user_token_1],
+        [&ctx.accounts.pool_token_0, &ctx.accounts.pool_token_1],
+        [&ctx.accounts.token_mint_0, &ctx.accounts.token_mint_1],
+        [&ctx.accounts.token_program_0, &ctx.accounts.token_program_1],
+        ctx.remaining_accounts,
+        amount_0,
+        amount_1,
+    )?;
+
+    emit_cpi!(events::BundledLiquidityChanged {
+        pool: pool.key(),
+        position_bundle: position_bundle.key(),
+        slot,
+        tick_lower: lower_tick,
+        tick_upper: upper_tick,
+        liquidity_delta: liquidity_amount as i128,
+        amount_0,
+        amount_1,
+    });
+
+    Ok((amount_0, amount_1))
+}
+
+pub fn decrease_bundled_position<'info>(
+    ctx: Context<'_, '_, '_, 'info, ModifyBundledPosition<'info>>,
+    slot: u8,
+    lower_tick: i32,
+    upper_tick: i32,
+    liquidity_amount: u128,
+) -> Result<(u64, u64)> {
+    require!(liquidity_amount > 0, ErrorCode::InsufficientInputAmount);
+    withdraw_bundled_liquidity(ctx, slot, lower_tick, upper_tick, Some(liquidity_amount))
+}
+
+/// Withdraws whatever liquidity remains in the slot and frees it.
+pub fn close_bundled_position<'info>(
+    ctx: Context<'_, '_, '_, 'info, ModifyBundledPosition<'info>>,
+    slot: u8,
+    lower_tick: i32,
+    upper_tick: i32,
+) -> Result<(u64, u64)> {
+    withdraw_bundled_liquidity(ctx, slot, lower_tick, upper_tick, None)
+}
+
+fn withdraw_bundled_liquidity<'info>(
+    ctx: Context<'_, '_, '_, 'info, ModifyBundledPosition<'info>>,
+    slot: u8,
+    lower_tick: i32,
+    upper_tick: i32,
+    liquidity_amount: Option<u128>,
+) -> Result<(u64, u64)> {
+    let pool = &mut ctx.accounts.pool;
+    pool.require_not_paused(PAUSE_WITHDRAW)?;
+
+    let position_bundle = &mut ctx.accounts.position_bundle;
+    let position = position_bundle.get_slot_mut(slot, lower_tick, upper_tick)?;
+    let liquidity_amount = liquidity_amount.unwrap_or(position.liquidity);
+
+    // A zero delta leaves the ticks as they are but still yields the
+    // range's fee growth, so fees can be settled on every withdrawal.
+    let (fee_growth_inside_0_x64, fee_growth_inside_1_x64) = TickArray::update_position_ticks(
+        &mut ctx.accounts.lower_tick_array,
+        &mut ctx.accounts.upper_tick_array,
+        pool,
+        lower_tick,
+        upper_tick,
+        -(liquidity_amount as i128),
+    )?;
+    position.accrue_fees(fee_growth_inside_0_x64, fee_growth_inside_1_x64)?;
+    let (fees_0, fees_1) = position.take_fees();
+    let (amount_0, amount_1) = if liquidity_amount > 0 {
+        update_bundled_liquidity(pool, position, -(liquidity_amount as i128))?
+    } else {
+        (0, 0)
+    };
+    // Accrued fees are paid out with the liquidity.
+    let amount_0 = amount_0.checked_add(fees_0).ok_or(ErrorCode::ArithmeticOverflow)?;
+    let amount_1 = amount_1.checked_add(fees_1).ok_or(ErrorCode::ArithmeticOverflow)?;
+    if position.liquidity == 0 {
+        position_bundle.close_slot(slot)?;
+    }
+
+    pool.lock()?;
+    pool.exit(&crate::ID)?;
+
+    if amount_0 > 0 {
+        transfer_from_vault_to_user(
+            pool,
+            &ctx.accounts.pool_token_0,
+            &ctx.accounts.user_token_0,
+            &ctx.accounts.token_mint_0,
+            &ctx.accounts.token_program_0,
+            ctx.remaining_accounts,
+            amount_0,
+        )?;
+    }
+
+    if amount_1 > 0 {
+        transfer_from_vault_to_user(
+            pool,
+            &ctx.accounts.pool_token_1,
+            &ctx.accounts.user_token_1,
+            &ctx.accounts.token_mint_1,
+            &ctx.accounts.token_program_1,
+            ctx.remaining_accounts,
+            amount_1,
+        )?;
+    }
+
+    pool.unlock();
+
+    emit_cpi!(events::BundledLiquidityChanged {
+        pool: pool.key(),
+        position_bundle: position_bundle.key(),
+        slot,
+        tick_lower: lower_tick,
+        tick_upper: upper_tick,
+        liquidity_delta: -(liquidity_amount as i128),
+        amount_0,
+        amount_1,
+    });
+
+    Ok((amount_0, amount_1))
+}
+
+/// Applies `liquidity_delta` to the slot and the pool, returning the token
+/// amounts it is worth at the current price. Callers update the ticks first.
+fn update_bundled_liquidity(
+    pool: &mut Pool,
+    position: &mut BundledPosition,
+    liquidity_delta: i128,
+) -> Result<(u64, u64)> {
+    position.liquidity = position
+        .liquidity
+        .checked_add_signed(liquidity_delta)
+        .ok_or(ErrorCode::InsufficientLiquidity)?;
+
+    pool.write_observation(Clock::get()?.unix_timestamp as u32)?;
+    pool.apply_liquidity_delta(position.tick_lower, position.tick_upper, liquidity_delta)?;
+
+    get_amounts_for_liquidity(
+        pool.sqrt_price_x96,
+        get_sqrt_price_from_tick(position.tick_lower)?,
+        get_sqrt_price_from_tick(position.tick_upper)?,
+        liquidity_delta.unsigned_abs(),
+    )
+}
+
+/// Pulls the deposit from the owner, grossed up for transfer fees so the
+/// vaults receive the full amounts. Returns the amounts actually charged.
+fn deposit<'info>(
+    pool: &mut Account<'info, Pool>,
+    owner: &Signer<'info>,
+    user_tokens: [&InterfaceAccount<'info, TokenAccount>; 2],
+    pool_tokens: [&InterfaceAccount<'info, TokenAccount>; 2],
+    mints: [&InterfaceAccount<'info, Mint>; 2],
+    token_programs: [&Interface<'info, TokenInterface>; 2],
+    remaining_accounts: &[AccountInfo<'info>],
+    amount_0: u64,
+    amount_1: u64,
+) -> Result<(u64, u64)> {
+    let amounts = [
+        amount_0
+            .checked_add(get_transfer_inverse_fee(mints[0], amount_0)?)
+            .ok_or(ErrorCode::ArithmeticOverflow)?,
+        amount_1
+            .checked_add(get_transfer_inverse_fee(mints[1], amount_1)?)
+            .ok_or(ErrorCode::ArithmeticOverflow)?,
+    ];
+
+    pool.lock()?;
+    pool.exit(&crate::ID)?;
+
+    for i in 0..2 {
+        if amounts[i] > 0 {
+            transfer_from_user_to_vault(
+                owner,
+                user_tokens[i],
+                pool_tokens[i],
+                mints[i],
+                token_programs[i],
+                remaining_accounts,
+                amounts[i],
+            )?;
+        }
+    }
+
+    pool.unlock();
+
+    Ok((amounts[0], amounts[1]))
+}
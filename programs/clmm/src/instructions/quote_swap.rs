@@ -0,0 +1,93 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::Mint;
+use crate::instructions::swap::{compute_swap, for_each_crossed_tick, SwapComputation};
+use crate::states::*;
+use crate::utils::ErrorCode;
+use crate::utils::math::*;
+
+/// Tick arrays the swap would walk through are passed as remaining accounts,
+/// as for `swap`; they are only read, to price the limit orders the swap
+/// would fill and count the initialized ticks crossed.
+#[derive(Accounts)]
+pub struct QuoteSwap<'info> {
+    #[account(
+        has_one = token_mint_0,
+        has_one = token_mint_1,
+    )]
+    pub pool: Account<'info, Pool>,
+    pub token_mint_0: InterfaceAccount<'info, Mint>,
+    pub token_mint_1: InterfaceAccount<'info, Mint>,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct SwapQuote {
+    pub amount_in: u64,
+    pub amount_out: u64,
+    pub fee_amount: u64,
+    pub sqrt_price_x96_after: u128,
+    pub tick_after: i32,
+    pub ticks_crossed: u32,
+    pub price_impact_bps: u64,
+}
+
+pub fn quote_swap<'info>(
+    ctx: Context<'_, '_, 'info, 'info, QuoteSwap<'info>>,
+    amount_in: u64,
+    swap_token_0_for_1: bool,
+) -> Result<SwapQuote> {
+    let pool = &ctx.accounts.pool;
+    pool.require_not_paused(PAUSE_SWAP)?;
+
+    let (input_mint, output_mint) = if swap_token_0_for_1 {
+        (&ctx.accounts.token_mint_0, &ctx.accounts.token_mint_1)
+    } else {
+        (&ctx.accounts.token_mint_1, &ctx.accounts.token_mint_0)
+    };
+
+    let SwapComputation {
+        amount_out_received,
+        fee_amount,
+        sqrt_price_x96_after,
+        ..
+    } = compute_swap(
+        pool,
+        input_mint,
+        output_mint,
+        ctx.remaining_accounts,
+        amount_in,
+        swap_token_0_for_1,
+    )?;
+
+    let tick_after = get_tick_at_sqrt_price(sqrt_price_x96_after)?;
+    // Walks the arrays exactly as the swap would, so a missing one fails the
+    // quote the same way.
+    let mut ticks_crossed: u32 = 0;
+    for_each_crossed_tick(
+        pool.key(),
+        pool.tick_spacing,
+        ctx.remaining_accounts,
+        pool.current_tick,
+        tick_after,
+        false,
+        |_, _| {
+            ticks_crossed += 1;
+            Ok(())
+        },
+    )?;
+
+    let price_delta = sqrt_price_x96_after.abs_diff(pool.sqrt_price_x96);
+    let price_impact_bps = price_delta
+        .checked_mul(10_000)
+        .ok_or(ErrorCode::ArithmeticOverflow)?
+        / pool.sqrt_price_x96;
+
+    Ok(SwapQuote {
+        amount_in,
+        amount_out: amount_out_received,
+        fee_amount,
+        sqrt_price_x96_after,
+        tick_after,
+        ticks_crossed,
+        price_impact_bps: u64::try_from(price_impact_bps).map_err(|_| ErrorCode::ArithmeticOverflow)?,
+    })
+}
@@ -0,0 +1,368 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::{Mint, TokenAccount, TokenInterface};
+use crate::events;
+use crate::instructions::swap::{swap_in_vaults, VaultSwap};
+use crate::states::*;
+use crate::utils::ErrorCode;
+use crate::utils::math::*;
+use crate::utils::token::*;
+
+#[event_cpi]
+#[derive(Accounts)]
+#[instruction(old_lower_tick: i32, old_upper_tick: i32, new_lower_tick: i32, new_upper_tick: i32)]
+pub struct RebalancePosition<'info> {
+    #[account(
+        mut,
+        has_one = token_mint_0,
+        has_one = token_mint_1,
+    )]
+    pub pool: Account<'info, Pool>,
+
+    // The same array may appear more than once: the old arrays are written
+    // back before the new ones are updated in place, then reloaded so Anchor
+    // writes back the final contents.
+    #[account(
+        mut,
+        seeds = [
+            b"tick_array",
+            pool.key().as_ref(),
+            &TickArray::get_starting_tick_index(old_lower_tick, pool.tick_spacing).to_le_bytes()
+        ],
+        bump = old_lower_tick_array.bump,
+        constraint = old_lower_tick_array.pool == pool.key() @ ErrorCode::InvalidTickArrayPool,
+    )]
+    pub old_lower_tick_array: Account<'info, TickArray>,
+
+    #[account(
+        mut,
+        seeds = [
+            b"tick_array",
+            pool.key().as_ref(),
+            &TickArray::get_starting_tick_index(old_upper_tick, pool.tick_spacing).to_le_bytes()
+        ],
+        bump = old_upper_tick_array.bump,
+        constraint = old_upper_tick_array.pool == pool.key() @ ErrorCode::InvalidTickArrayPool,
+    )]
+    pub old_upper_tick_array: Account<'info, TickArray>,
+
+    /// CHECK: tick array PDA holding `new_lower_tick`, created if needed and
+    /// updated in place.
+    #[account(
+        mut,
+        seeds = [
+            b"tick_array",
+            pool.key().as_ref(),
+            &TickArray::get_starting_tick_index(new_lower_tick, pool.tick_spacing).to_le_bytes()
+        ],
+        bump,
+    )]
+    pub new_lower_tick_array: UncheckedAccount<'info>,
+
+    /// CHECK: tick array PDA holding `new_upper_tick`, created if needed and
+    /// updated in place.
+    #[account(
+        mut,
+        seeds = [
+            b"tick_array",
+            pool.key().as_ref(),
+            &TickArray::get_starting_tick_index(new_upper_tick, pool.tick_spacing).to_le_bytes()
+        ],
+        bump,
+    )]
+    pub new_upper_tick_array: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        close = owner,
+        seeds = [
+            b"position",
+            owner.key().as_ref(),
+            pool.key().as_ref(),
+            &old_lower_tick.to_le_bytes(),
+            &old_upper_tick.to_le_bytes(),
+        ],
+        bump = old_position.bump,
+        has_one = owner @ ErrorCode::InvalidPositionOwner,
+        has_one = pool @ ErrorCode::InvalidPositionRange,
+        constraint = old_position.is_authorized(&payer.key()) @ ErrorCode::InvalidPositionOwner,
+    )]
+    pub old_position: Account<'info, Position>,
+
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = Position::SPACE,
+        seeds = [
+            b"position",
+            owner.key().as_ref(),
+            pool.key().as_ref(),
+            &new_lower_tick.to_le_bytes(),
+            &new_upper_tick.to_le_bytes(),
+        ],
+        bump,
+    )]
+    pub new_position: Account<'info, Position>,
+
+    #[account(
+        mut,
+        constraint = user_token_0.mint == pool.token_mint_0 @ ErrorCode::InvalidTokenMint,
+        constraint = user_token_0.owner == owner.key() @ ErrorCode::InvalidTokenAccountOwner,
+    )]
+    pub user_token_0: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        constraint = user_token_1.mint == pool.token_mint_1 @ ErrorCode::InvalidTokenMint,
+        constraint = user_token_1.owner == owner.key() @ ErrorCode::InvalidTokenAccountOwner,
+    )]
+    pub user_token_1: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        address = pool.token_vault_0 @ ErrorCode::InvalidPoolVault,
+    )]
+    pub pool_token_0: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        address = pool.token_vault_1 @ ErrorCode::InvalidPoolVault,
+    )]
+    pub pool_token_1: InterfaceAccount<'info, TokenAccount>,
+
+    /// CHECK: the position owner, checked by `has_one` on `old_position`.
+    /// Receives the closed position's rent; refunds go to its token accounts.
+    #[account(mut)]
+    pub owner: UncheckedAccount<'info>,
+
+    /// The owner or the position's operator.
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub token_mint_0: InterfaceAccount<'info, Mint>,
+    pub token_mint_1: InterfaceAccount<'info, Mint>,
+    pub system_program: Program<'info, System>,
+    pub token_program_0: Interface<'info, TokenInterface>,
+    pub token_program_1: Interface<'info, TokenInterface>,
+}
+
+/// Moves all liquidity of a position to a new range. The withdrawn amounts
+/// stay in the vaults; `swap_amount_in` of one side can be swapped inside the
+/// pool first, and whatever the new range cannot use is refunded to the owner.
+/// The position's operator may rebalance on the owner's behalf.
+pub fn rebalance_position<'info>(
+    ctx: Context<'_, '_, 'info, 'info, RebalancePosition<'info>>,
+    old_lower_tick: i32,
+    old_upper_tick: i32,
+    new_lower_tick: i32,
+    new_upper_tick: i32,
+    swap_amount_in: u64,
+    swap_token_0_for_1: bool,
+    min_liquidity: u128,
+) -> Result<u128> {
+    let pool = &mut ctx.accounts.pool;
+    pool.require_not_paused(PAUSE_WITHDRAW | PAUSE_DEPOSIT)?;
+
+    require!(
+        new_lower_tick < new_upper_tick
+            && new_lower_tick >= MIN_TICK
+            && new_upper_tick <= MAX_TICK
+            && new_lower_tick % pool.tick_spacing == 0
+            && new_upper_tick % pool.tick_spacing == 0,
+        ErrorCode::InvalidTickRange
+    );
+    require!(
+        old_lower_tick != new_lower_tick || old_upper_tick != new_upper_tick,
+        ErrorCode::InvalidPositionRange
+    );
+
+    let unix_timestamp = Clock::get()?.unix_timestamp;
+    ctx.accounts.old_position.require_unlocked(unix_timestamp)?;
+    let old_liquidity = ctx.accounts.old_position.liquidity;
+    require!(old_liquidity > 0, ErrorCode::NoLiquidityToRemove);
+
+    let now = unix_timestamp as u32;
+    let (mut amount_0, mut amount_1) = get_amounts_for_liquidity(
+        pool.sqrt_price_x96,
+        get_sqrt_price_from_tick(old_lower_tick)?,
+        get_sqrt_price_from_tick(old_upper_tick)?,
+        old_liquidity,
+    )?;
+
+    let (fee_growth_inside_0_x64, fee_growth_inside_1_x64) = TickArray::update_position_ticks(
+        &mut ctx.accounts.old_lower_tick_array,
+        &mut ctx.accounts.old_upper_tick_array,
+        pool,
+        old_lower_tick,
+        old_upper_tick,
+        -(old_liquidity as i128),
+    )?;
+    ctx.accounts
+        .old_position
+        .accrue_fees(fee_growth_inside_0_x64, fee_growth_inside_1_x64)?;
+    ctx.accounts.old_lower_tick_array.exit(&crate::ID)?;
+    ctx.accounts.old_upper_tick_array.exit(&crate::ID)?;
+    ctx.accounts.old_position.liquidity = 0;
+
+    pool.write_observation(now)?;
+    pool.apply_liquidity_delta(old_lower_tick, old_upper_tick, -(old_liquidity as i128))?;
+
+    if swap_amount_in > 0 {
+        let (available_in, available_out) = if swap_token_0_for_1 {
+            (&mut amount_0, &mut amount_1)
+        } else {
+            (&mut amount_1, &mut amount_0)
+        };
+        require!(swap_amount_in <= *available_in, ErrorCode::InsufficientInputAmount);
+
+        let tick_arrays: Vec<AccountInfo<'info>> = [
+            ctx.accounts.old_lower_tick_array.to_account_info(),
+            ctx.accounts.old_upper_tick_array.to_account_info(),
+            ctx.accounts.new_lower_tick_array.to_account_info(),
+            ctx.accounts.new_upper_tick_array.to_account_info(),
+        ]
+        .into_iter()
+        .chain(ctx.remaining_accounts.iter().cloned())
+        .collect();
+        let VaultSwap {
+            amount_out,
+            swap_event,
+            filled_limit_orders,
+        } = swap_in_vaults(
+            pool,
+            ctx.accounts.payer.key(),
+            &tick_arrays,
+            swap_amount_in,
+            swap_token_0_for_1,
+            now,
+        )?;
+        *available_in -= swap_amount_in;
+        *available_out = available_out
+            .checked_add(amount_out)
+            .ok_or(ErrorCode::ArithmeticOverflow)?;
+
+        emit_cpi!(swap_event);
+        for filled in filled_limit_orders {
+            emit_cpi!(filled);
+        }
+    }
+
+    let new_lower_sqrt_price_x96 = get_sqrt_price_from_tick(new_lower_tick)?;
+    let new_upper_sqrt_price_x96 = get_sqrt_price_from_tick(new_upper_tick)?;
+    let new_liquidity = get_liquidity_for_amounts(
+        pool.sqrt_price_x96,
+        new_lower_sqrt_price_x96,
+        new_upper_sqrt_price_x96,
+        amount_0,
+        amount_1,
+    )?;
+    require!(
+        new_liquidity > 0 && new_liquidity >= min_liquidity,
+        ErrorCode::SlippageExceeded
+    );
+    let (used_0, used_1) = get_amounts_for_liquidity(
+        pool.sqrt_price_x96,
+        new_lower_sqrt_price_x96,
+        new_upper_sqrt_price_x96,
+        new_liquidity,
+    )?;
+
+    for (tick_array, tick) in [
+        (&ctx.accounts.new_lower_tick_array, new_lower_tick),
+        (&ctx.accounts.new_upper_tick_array, new_upper_tick),
+    ] {
+        TickArray::init_if_needed(
+            &ctx.accounts.payer,
+            &ctx.accounts.system_program,
+            tick_array,
+            pool.key(),
+            tick,
+            pool.tick_spacing,
+        )?;
+    }
+    let (fee_growth_inside_0_x64, fee_growth_inside_1_x64) = TickArray::update_position_ticks_in_place(
+        &ctx.accounts.new_lower_tick_array,
+        &ctx.accounts.new_upper_tick_array,
+        pool,
+        new_lower_tick,
+        new_upper_tick,
+        new_liquidity as i128,
+    )?;
+    ctx.accounts.old_lower_tick_array.reload()?;
+    ctx.accounts.old_upper_tick_array.reload()?;
+
+    let owner = ctx.accounts.owner.key();
+    let operator = ctx.accounts.old_position.operator;
+    let new_position = &mut ctx.accounts.new_position;
+    // Fees accrued in the old range follow the liquidity to the new one.
+    new_position.accrue_fees(fee_growth_inside_0_x64, fee_growth_inside_1_x64)?;
+    new_position.absorb_fees(&mut ctx.accounts.old_position)?;
+    if new_position.liquidity == 0 && new_position.owner == Pubkey::default() {
+        new_position.owner = owner;
+        new_position.operator = operator;
+        new_position.pool = pool.key();
+        new_position.tick_lower = new_lower_tick;
+        new_position.tick_upper = new_upper_tick;
+        new_position.liquidity = new_liquidity;
+        new_position.bump = ctx.bumps.new_position;
+    } else {
+        require!(new_position.owner == owner, ErrorCode::InvalidPositionOwner);
+        new_position.liquidity = new_position
+            .liquidity
+            .checked_add(new_liquidity)
+            .ok_or(ErrorCode::ArithmeticOverflow)?;
+    }
+
+    pool.apply_liquidity_delta(new_lower_tick, new_upper_tick, new_liquidity as i128)?;
+
+    let refund_0 = amount_0.checked_sub(used_0).ok_or(ErrorCode::ArithmeticOverflow)?;
+    let refund_1 = amount_1.checked_sub(used_1).ok_or(ErrorCode::ArithmeticOverflow)?;
+
+    pool.lock()?;
+    pool.exit(&crate::ID)?;
+
+    if refund_0 > 0 {
+        transfer_from_vault_to_user(
+            pool,
+            &ctx.accounts.pool_token_0,
+            &ctx.accounts.user_token_0,
+            &ctx.accounts.token_mint_0,
+            &ctx.accounts.token_program_0,
+            ctx.remaining_accounts,
+            refund_0,
+        )?;
+    }
+
+    if refund_1 > 0 {
+        transfer_from_vault_to_user(
+            pool,
+            &ctx.accounts.pool_token_1,
+            &ctx.accounts.user_token_1,
+            &ctx.accounts.token_mint_1,
+            &ctx.accounts.token_program_1,
+            ctx.remaining_accounts,
+            refund_1,
+        )?;
+    }
+
+    pool.unlock();
+
+    emit_cpi!(events::LiquidityDecreased {
+        pool: pool.key(),
+        position: ctx.accounts.old_position.key(),
+        owner,
+        tick_lower: old_lower_tick,
+        tick_upper: old_upper_tick,
+        liquidity: old_liquidity,
+        amount_0: refund_0,
+        amount_1: refund_1,
+    });
+    emit_cpi!(events::LiquidityIncreased {
+        pool: pool.key(),
+        position: ctx.accounts.new_position.key(),
+        owner,
+        tick_lower: new_lower_tick,
+        tick_upper: new_upper_tick,
+        liquidity: new_liquidity,
+        amount_0: used_0,
+        amount_1: used_1,
+    });
+
+    Ok(new_liquidity)
+}
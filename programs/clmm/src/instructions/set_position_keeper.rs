@@ -0,0 +1,33 @@
+use anchor_lang::prelude::*;
+use crate::events;
+use crate::states::*;
+use crate::utils::ErrorCode;
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct SetPositionKeeper<'info> {
+    pub owner: Signer<'info>,
+
+    #[account(
+        mut,
+        has_one = owner @ ErrorCode::InvalidPositionOwner,
+    )]
+    pub position: Account<'info, Position>,
+}
+
+/// Lets `keeper` compound the position's fees for a bounty. Unlike an
+/// operator, a keeper cannot withdraw or move liquidity. Passing the default
+/// pubkey revokes the current keeper.
+pub fn set_position_keeper(ctx: Context<SetPositionKeeper>, keeper: Pubkey) -> Result<()> {
+    let position = &mut ctx.accounts.position;
+    position.keeper = keeper;
+
+    emit_cpi!(events::PositionKeeperUpdated {
+        pool: position.pool,
+        position: position.key(),
+        owner: position.owner,
+        keeper,
+    });
+
+    Ok(())
+}
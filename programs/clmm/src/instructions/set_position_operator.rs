@@ -0,0 +1,32 @@
+use anchor_lang::prelude::*;
+use crate::events;
+use crate::states::*;
+use crate::utils::ErrorCode;
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct SetPositionOperator<'info> {
+    pub owner: Signer<'info>,
+
+    #[account(
+        mut,
+        has_one = owner @ ErrorCode::InvalidPositionOwner,
+    )]
+    pub position: Account<'info, Position>,
+}
+
+/// Delegates liquidity management of the position to `operator`. Passing the
+/// default pubkey revokes the current operator.
+pub fn set_position_operator(ctx: Context<SetPositionOperator>, operator: Pubkey) -> Result<()> {
+    let position = &mut ctx.accounts.position;
+    position.operator = operator;
+
+    emit_cpi!(events::PositionOperatorUpdated {
+        pool: position.pool,
+        position: position.key(),
+        owner: position.owner,
+        operator,
+    });
+
+    Ok(())
+}
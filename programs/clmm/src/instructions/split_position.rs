@@ -0,0 +1,107 @@
+use anchor_lang::prelude::*;
+use crate::events;
+use crate::states::*;
+use crate::utils::ErrorCode;
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct SplitPosition<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    /// CHECK: owner of the carved-out position; only its key is used.
+    #[account(constraint = new_owner.key() != owner.key() @ ErrorCode::InvalidPositionOwner)]
+    pub new_owner: UncheckedAccount<'info>,
+
+    pub pool: Account<'info, Pool>,
+
+    #[account(
+        mut,
+        has_one = owner @ ErrorCode::InvalidPositionOwner,
+        has_one = pool @ ErrorCode::InvalidPositionRange,
+    )]
+    pub position: Account<'info, Position>,
+
+    #[account(
+        seeds = [
+            b"tick_array",
+            pool.key().as_ref(),
+            &TickArray::get_starting_tick_index(position.tick_lower, pool.tick_spacing).to_le_bytes()
+        ],
+        bump = lower_tick_array.bump,
+    )]
+    pub lower_tick_array: Account<'info, TickArray>,
+
+    #[account(
+        seeds = [
+            b"tick_array",
+            pool.key().as_ref(),
+            &TickArray::get_starting_tick_index(position.tick_upper, pool.tick_spacing).to_le_bytes()
+        ],
+        bump = upper_tick_array.bump,
+    )]
+    pub upper_tick_array: Account<'info, TickArray>,
+
+    #[account(
+        init_if_needed,
+        payer = owner,
+        space = Position::SPACE,
+        seeds = [
+            b"position",
+            new_owner.key().as_ref(),
+            pool.key().as_ref(),
+            &position.tick_lower.to_le_bytes(),
+            &position.tick_upper.to_le_bytes(),
+        ],
+        bump,
+    )]
+    pub new_position: Account<'info, Position>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Moves `liquidity` into a same-range position of `new_owner`. Ticks and
+/// pool liquidity are untouched since the total per tick does not change.
+pub fn split_position(ctx: Context<SplitPosition>, liquidity: u128) -> Result<()> {
+    let position = &mut ctx.accounts.position;
+    let new_position = &mut ctx.accounts.new_position;
+
+    require!(liquidity > 0, ErrorCode::InsufficientInputAmount);
+    let (fee_growth_inside_0_x64, fee_growth_inside_1_x64) = TickArray::get_fee_growth_inside(
+        &ctx.accounts.lower_tick_array,
+        &ctx.accounts.upper_tick_array,
+        &ctx.accounts.pool,
+        position.tick_lower,
+        position.tick_upper,
+    )?;
+    position.accrue_fees(fee_growth_inside_0_x64, fee_growth_inside_1_x64)?;
+    position.liquidity = position
+        .liquidity
+        .checked_sub(liquidity)
+        .ok_or(ErrorCode::InsufficientLiquidity)?;
+
+    if new_position.owner == Pubkey::default() {
+        new_position.owner = ctx.accounts.new_owner.key();
+        new_position.pool = position.pool;
+        new_position.tick_lower = position.tick_lower;
+        new_position.tick_upper = position.tick_upper;
+        new_position.bump = ctx.bumps.new_position;
+        new_position.lock_until = position.lock_until;
+    }
+    new_position.require_can_absorb(position, Clock::get()?.unix_timestamp)?;
+    new_position.accrue_fees(fee_growth_inside_0_x64, fee_growth_inside_1_x64)?;
+    new_position.liquidity = new_position
+        .liquidity
+        .checked_add(liquidity)
+        .ok_or(ErrorCode::ArithmeticOverflow)?;
+
+    emit_cpi!(events::PositionSplit {
+        pool: position.pool,
+        position: position.key(),
+        new_position: new_position.key(),
+        new_owner: new_position.owner,
+        liquidity,
+    });
+
+    Ok(())
+}
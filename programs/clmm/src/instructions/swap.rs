@@ -1,24 +1,56 @@
 use anchor_lang::prelude::*;
-use anchor_spl::token::{self, Transfer};
-use anchor_spl::token::{Token, TokenAccount};
+use anchor_spl::token_interface::{Mint, TokenAccount, TokenInterface};
+use crate::events;
 use crate::states::*;
 use crate::utils::ErrorCode;
 use crate::utils::math::*;
+use crate::utils::token::*;
 
+#[event_cpi]
 #[derive(Accounts)]
 #[instruction(amount_in: u64, swap_token_0_for_1: bool, amount_out_minimum: u64)]
 pub struct Swap<'info> {
-    #[account(mut)]
+    #[account(
+        mut,
+        has_one = token_mint_0,
+        has_one = token_mint_1,
+    )]
     pub pool: Account<'info, Pool>,
-    #[account(mut)]
-    pub user_token_0: Account<'info, TokenAccount>,
-    #[account(mut)]
-    pub user_token_1: Account<'info, TokenAccount>,
-    #[account(mut)]
-    pub pool_token_0: Account<'info, TokenAccount>,
-    #[account(mut)]
-    pub pool_token_1: Account<'info, TokenAccount>,
+    #[account(
+        mut,
+        constraint = user_token_0.mint == pool.token_mint_0 @ ErrorCode::InvalidTokenMint,
+        constraint = user_token_0.owner == payer.key() @ ErrorCode::InvalidTokenAccountOwner,
+    )]
+    pub user_token_0: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        constraint = user_token_1.mint == pool.token_mint_1 @ ErrorCode::InvalidTokenMint,
+        constraint = user_token_1.owner == payer.key() @ ErrorCode::InvalidTokenAccountOwner,
+    )]
+    pub user_token_1: InterfaceAccount<'info, TokenAccount>,
+    // Receives the output; any holder of the output mint may be paid.
+    #[account(
+        mut,
+        constraint = recipient_token.mint == if swap_token_0_for_1 {
+            pool.token_mint_1
+        } else {
+            pool.token_mint_0
+        } @ ErrorCode::InvalidTokenMint,
+    )]
+    pub recipient_token: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        address = pool.token_vault_0 @ ErrorCode::InvalidPoolVault,
+    )]
+    pub pool_token_0: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        address = pool.token_vault_1 @ ErrorCode::InvalidPoolVault,
+    )]
+    pub pool_token_1: InterfaceAccount<'info, TokenAccount>,
 
+    /// CHECK: tick array holding the current price, possibly not created
+    /// yet. Arrays further along the swap follow in the remaining accounts.
     #[account(
         mut,
         constraint = tick_array.key() == Pubkey::find_program_address(
@@ -30,100 +62,468 @@ pub struct Swap<'info> {
             &crate::ID
         ).0 @ ErrorCode::InvalidTickArrayAccount
     )]
-    pub tick_array: Account<'info, TickArray>,
+    pub tick_array: UncheckedAccount<'info>,
 
+    #[account(mut)]
     pub payer: Signer<'info>,
+    pub token_mint_0: InterfaceAccount<'info, Mint>,
+    pub token_mint_1: InterfaceAccount<'info, Mint>,
     pub system_program: Program<'info, System>,
-    pub token_program: Program<'info, Token>,
+    pub token_program_0: Interface<'info, TokenInterface>,
+    pub token_program_1: Interface<'info, TokenInterface>,
     pub rent: Sysvar<'info, Rent>,
 }
 
+pub struct SwapComputation {
+    pub amount_in_received: u64,
+    pub amount_out: u64,
+    pub amount_out_received: u64,
+    pub fee_amount: u64,
+    pub sqrt_price_x96_after: u128,
+}
+
+/// Runs the swap math for `amount_in` against the pool without touching any
+/// state, so `swap` and `quote_swap` always agree. Only the amount that
+/// actually reaches the vault after transfer fees is swapped. `tick_arrays` is
+/// as for `for_each_crossed_tick`.
+pub fn compute_swap(
+    pool: &Account<Pool>,
+    input_mint: &InterfaceAccount<Mint>,
+    output_mint: &InterfaceAccount<Mint>,
+    tick_arrays: &[AccountInfo],
+    amount_in: u64,
+    swap_token_0_for_1: bool,
+) -> Result<SwapComputation> {
+    require!(pool.global_liquidity > 0, ErrorCode::InsufficientPoolLiquidity);
+    require!(amount_in > 0, ErrorCode::InsufficientInputAmount);
+
+    let amount_in_received = amount_in
+        .checked_sub(get_transfer_fee(input_mint, amount_in)?)
+        .ok_or(ErrorCode::ArithmeticOverflow)?;
+
+    let (amount_out, fee_amount, sqrt_price_x96_after) = split_swap(
+        pool,
+        tick_arrays,
+        amount_in_received,
+        swap_token_0_for_1,
+        pool.fee_rate(Clock::get()?.unix_timestamp as u32),
+    )?;
+
+    let amount_out_received = amount_out
+        .checked_sub(get_transfer_fee(output_mint, amount_out)?)
+        .ok_or(ErrorCode::ArithmeticOverflow)?;
+
+    Ok(SwapComputation {
+        amount_in_received,
+        amount_out,
+        amount_out_received,
+        fee_amount,
+        sqrt_price_x96_after,
+    })
+}
+
+/// Splits `amount_in` between the limit orders the swap fills and the curve,
+/// returning the total output, the curve's fee and the price after. The
+/// orders are paid from the input and their tokens go to the swapper, so the
+/// liquidity providers only take the curve's side of the trade. The toy curve
+/// moves the price by the same step whatever the amount, so the ticks crossed
+/// are known before the input is split.
+fn split_swap(
+    pool: &Account<Pool>,
+    tick_arrays: &[AccountInfo],
+    amount_in: u64,
+    swap_token_0_for_1: bool,
+    fee_rate: u32,
+) -> Result<(u64, u64, u128)> {
+    let (_, _, _, sqrt_price_x96_after) = swap_segment(
+        pool.sqrt_price_x96,
+        pool.global_liquidity,
+        amount_in,
+        swap_token_0_for_1,
+        fee_rate,
+    )?;
+
+    let mut budget = amount_in;
+    let mut orders_amount_out: u64 = 0;
+    for_each_crossed_tick(
+        pool.key(),
+        pool.tick_spacing,
+        tick_arrays,
+        pool.current_tick,
+        get_tick_at_sqrt_price(sqrt_price_x96_after)?,
+        false,
+        |tick, tick_info| {
+            if let Some((filled, proceeds)) = tick_info.fill_limit_orders(tick, !swap_token_0_for_1, budget)? {
+                budget -= proceeds;
+                orders_amount_out = orders_amount_out
+                    .checked_add(filled)
+                    .ok_or(ErrorCode::ArithmeticOverflow)?;
+            }
+            Ok(())
+        },
+    )?;
+
+    let (_, curve_amount_out, fee_amount, _) = swap_segment(
+        pool.sqrt_price_x96,
+        pool.global_liquidity,
+        budget,
+        swap_token_0_for_1,
+        fee_rate,
+    )?;
+    let amount_out = curve_amount_out
+        .checked_add(orders_amount_out)
+        .ok_or(ErrorCode::ArithmeticOverflow)?;
+    Ok((amount_out, fee_amount, sqrt_price_x96_after))
+}
+
+/// Visits every initialized tick a price moving from `tick_before` to
+/// `tick_after` crosses, in crossing order. `tick_arrays` must hold each tick
+/// array of the pool the price passes through; arrays never created are
+/// passed as their empty addresses. Other accounts are ignored. Changes
+/// `visit` makes to the ticks are written back when `write_back` is set.
+pub fn for_each_crossed_tick(
+    pool_key: Pubkey,
+    tick_spacing: i32,
+    tick_arrays: &[AccountInfo],
+    tick_before: i32,
+    tick_after: i32,
+    write_back: bool,
+    mut visit: impl FnMut(i32, &mut TickInfo) -> Result<()>,
+) -> Result<()> {
+    if tick_before == tick_after {
+        return Ok(());
+    }
 
-    pub fn swap(
-        ctx: Context<Swap>,
+    // Ticks in (low, high] are crossed, upwards or downwards.
+    let upwards = tick_after > tick_before;
+    let (low, high) = if upwards {
+        (tick_before, tick_after)
+    } else {
+        (tick_after, tick_before)
+    };
+    let ticks_in_array = tick_spacing * TICKS_PER_ARRAY as i32;
+    let first_start = TickArray::get_starting_tick_index(low + 1, tick_spacing);
+    let last_start = TickArray::get_starting_tick_index(high, tick_spacing);
+    let mut starts: Vec<i32> = (0..=(last_start - first_start) / ticks_in_array)
+        .map(|i| first_start + i * ticks_in_array)
+        .collect();
+    if !upwards {
+        starts.reverse();
+    }
+
+    for start in starts {
+        let found = tick_arrays.iter().find(|account| {
+            TickArray::load(account)
+                .map(|tick_array| tick_array.pool == pool_key && tick_array.starting_tick == start)
+                .unwrap_or(false)
+        });
+        let Some(account) = found else {
+            // Skipping an array is only safe if it holds no ticks at all.
+            let (address, _) = Pubkey::find_program_address(
+                &[b"tick_array", pool_key.as_ref(), &start.to_le_bytes()],
+                &crate::ID,
+            );
+            require!(
+                tick_arrays
+                    .iter()
+                    .any(|account| account.key() == address && account.data_is_empty()),
+                ErrorCode::MissingTickArray
+            );
+            continue;
+        };
+
+        let mut tick_array = TickArray::load(account)?;
+        let mut offsets: Vec<usize> = (0..TICKS_PER_ARRAY).collect();
+        if !upwards {
+            offsets.reverse();
+        }
+        for offset in offsets {
+            let tick = start + offset as i32 * tick_spacing;
+            let tick_info = &mut tick_array.ticks[offset];
+            if tick > low && tick <= high && tick_info.initialized {
+                visit(tick, tick_info)?;
+            }
+        }
+        if write_back {
+            tick_array.store(account)?;
+        }
+    }
+    Ok(())
+}
+
+/// Moves the pool to `sqrt_price_x96`, crossing every initialized tick on the
+/// way so `global_liquidity` stays the liquidity in range, and fills the limit
+/// orders resting on crossed ticks that `limit_order_budget` of the swapper's
+/// input pays for, as `split_swap` previewed, returning one event per tick
+/// filled. `tick_arrays` is as for `for_each_crossed_tick`.
+pub fn move_price(
+    pool: &mut Account<Pool>,
+    tick_arrays: &[AccountInfo],
+    sqrt_price_x96: u128,
+    block_timestamp: u32,
+    limit_order_budget: u64,
+) -> Result<Vec<events::LimitOrdersFilled>> {
+    let tick_before = pool.current_tick;
+    pool.update_sqrt_price(sqrt_price_x96, block_timestamp)?;
+    let tick_after = pool.current_tick;
+    let upwards = tick_after > tick_before;
+
+    let pool_key = pool.key();
+    let tick_spacing = pool.tick_spacing;
+    let mut budget = limit_order_budget;
+    let mut filled_limit_orders = Vec::new();
+    for_each_crossed_tick(
+        pool_key,
+        tick_spacing,
+        tick_arrays,
+        tick_before,
+        tick_after,
+        true,
+        |tick, tick_info| {
+            let liquidity_net =
+                tick_info.cross(pool.fee_growth_global_0_x64, pool.fee_growth_global_1_x64);
+            pool.global_liquidity = if upwards {
+                pool.global_liquidity.checked_add_signed(liquidity_net)
+            } else {
+                pool.global_liquidity.checked_add_signed(-liquidity_net)
+            }
+            .ok_or(ErrorCode::ArithmeticOverflow)?;
+
+            let epoch = tick_info.limit_order_epoch;
+            if let Some((amount_in, amount_out)) = tick_info.fill_limit_orders(tick, upwards, budget)? {
+                budget -= amount_out;
+                filled_limit_orders.push(events::LimitOrdersFilled {
+                    pool: pool_key,
+                    tick,
+                    epoch,
+                    zero_for_one: upwards,
+                    amount_in,
+                    amount_out,
+                });
+            }
+            Ok(())
+        },
+    )?;
+    Ok(filled_limit_orders)
+}
+
+pub struct VaultSwap {
+    pub amount_out: u64,
+    pub swap_event: events::Swap,
+    pub filled_limit_orders: Vec<events::LimitOrdersFilled>,
+}
+
+/// Swaps balances the vaults already hold on the user's behalf, such as
+/// freshly withdrawn liquidity, and moves the pool price across `tick_arrays`
+/// as `move_price` does. No tokens move, so no transfer fees apply. The
+/// caller emits the returned events.
+pub fn swap_in_vaults<'info>(
+    pool: &mut Account<'info, Pool>,
+    sender: Pubkey,
+    tick_arrays: &[AccountInfo<'info>],
+    amount_in: u64,
+    swap_token_0_for_1: bool,
+    block_timestamp: u32,
+) -> Result<VaultSwap> {
+    pool.require_not_paused(PAUSE_SWAP)?;
+
+    let (amount_out, fee_amount, sqrt_price_x96_after) = split_swap(
+        pool,
+        tick_arrays,
+        amount_in,
+        swap_token_0_for_1,
+        pool.fee_rate(block_timestamp),
+    )?;
+
+    let sqrt_price_x96_before = pool.sqrt_price_x96;
+    let tick_before = pool.current_tick;
+    pool.credit_swap_fee(fee_amount, swap_token_0_for_1)?;
+    let filled_limit_orders = move_price(
+        pool,
+        tick_arrays,
+        sqrt_price_x96_after,
+        block_timestamp,
+        amount_in,
+    )?;
+
+    Ok(VaultSwap {
+        amount_out,
+        swap_event: events::Swap {
+            pool: pool.key(),
+            sender,
+            swap_token_0_for_1,
+            amount_in,
+            amount_out,
+            sqrt_price_x96_before,
+            sqrt_price_x96_after: pool.sqrt_price_x96,
+            tick_before,
+            tick_after: pool.current_tick,
+            liquidity: pool.global_liquidity,
+        },
+        filled_limit_orders,
+    })
+}
+
+    pub fn swap<'info>(
+        ctx: Context<'_, '_, 'info, 'info, Swap<'info>>,
         amount_in: u64,
         swap_token_0_for_1: bool,
         amount_out_minimum: u64,
+        native_sol: bool,
     ) -> Result<u64> {
         let pool = &mut ctx.accounts.pool;
+        pool.require_not_paused(PAUSE_SWAP)?;
 
-        require!(pool.global_liquidity > 0, ErrorCode::InsufficientPoolLiquidity);
-        require!(amount_in > 0, ErrorCode::InsufficientInputAmount);
+        let (input_mint, output_mint) = if swap_token_0_for_1 {
+            (&ctx.accounts.token_mint_0, &ctx.accounts.token_mint_1)
+        } else {
+            (&ctx.accounts.token_mint_1, &ctx.accounts.token_mint_0)
+        };
 
-        let (amount_in_used, amount_out_calculated, new_sqrt_price_x96) = swap_segment(
-            pool.sqrt_price_x96,
-            pool.global_liquidity,
+        let wrap_input = native_sol && is_native_mint(&input_mint.key());
+        let unwrap_output = native_sol && is_native_mint(&output_mint.key());
+        require!(
+            !native_sol || wrap_input || unwrap_output,
+            ErrorCode::NativeMintNotInPool
+        );
+        // Unwrapping closes the output account, which only its owner can do.
+        require!(
+            !unwrap_output || ctx.accounts.recipient_token.owner == ctx.accounts.payer.key(),
+            ErrorCode::InvalidTokenAccountOwner
+        );
+
+        let tick_arrays: Vec<AccountInfo<'info>> = std::iter::once(ctx.accounts.tick_array.to_account_info())
+            .chain(ctx.remaining_accounts.iter().cloned())
+            .collect();
+        let SwapComputation {
+            amount_in_received,
+            amount_out: amount_out_calculated,
+            amount_out_received,
+            fee_amount,
+            sqrt_price_x96_after: new_sqrt_price_x96,
+        } = compute_swap(
+            pool,
+            input_mint,
+            output_mint,
+            &tick_arrays,
             amount_in,
             swap_token_0_for_1,
         )?;
 
         require!(
-            amount_out_calculated >= amount_out_minimum,
+            amount_out_received >= amount_out_minimum,
             ErrorCode::SlippageExceeded
         );
 
-        let signer_seeds: &[&[&[u8]]] = &[&[
-            b"pool",
-            pool.token_mint_0.as_ref(),
-            pool.token_mint_1.as_ref(),
-            &pool.tick_spacing.to_le_bytes(),
-            &[pool.bump],
-        ]];
+        let sqrt_price_x96_before = pool.sqrt_price_x96;
+        let tick_before = pool.current_tick;
+
+        pool.lock()?;
+        pool.exit(&crate::ID)?;
 
         if swap_token_0_for_1 {
-            token::transfer(
-                CpiContext::new(
-                    ctx.accounts.token_program.to_account_info(),
-                    Transfer {
-                        from: ctx.accounts.user_token_0.to_account_info(),
-                        to: ctx.accounts.pool_token_0.to_account_info(),
-                        authority: ctx.accounts.payer.to_account_info(),
-                    },
-                ),
-                amount_in_used,
+            if wrap_input {
+                wrap_native(
+                    &ctx.accounts.payer,
+                    &ctx.accounts.user_token_0,
+                    &ctx.accounts.token_program_0,
+                    &ctx.accounts.system_program,
+                    amount_in,
+                )?;
+            }
+
+            transfer_from_user_to_vault(
+                &ctx.accounts.payer,
+                &ctx.accounts.user_token_0,
+                &ctx.accounts.pool_token_0,
+                &ctx.accounts.token_mint_0,
+                &ctx.accounts.token_program_0,
+                ctx.remaining_accounts,
+                amount_in,
             )?;
 
-            token::transfer(
-                CpiContext::new_with_signer(
-                    ctx.accounts.token_program.to_account_info(),
-                    Transfer {
-                        from: ctx.accounts.pool_token_1.to_account_info(),
-                        to: ctx.accounts.user_token_1.to_account_info(),
-                        authority: pool.to_account_info(),
-                    },
-                    signer_seeds,
-                ),
+            transfer_from_vault_to_user(
+                pool,
+                &ctx.accounts.pool_token_1,
+                &ctx.accounts.recipient_token,
+                &ctx.accounts.token_mint_1,
+                &ctx.accounts.token_program_1,
+                ctx.remaining_accounts,
                 amount_out_calculated,
             )?;
+
+            if unwrap_output {
+                unwrap_native(
+                    &ctx.accounts.payer,
+                    &ctx.accounts.recipient_token,
+                    &ctx.accounts.token_program_1,
+                )?;
+            }
         } else {
-            token::transfer(
-                CpiContext::new(
-                    ctx.accounts.token_program.to_account_info(),
-                    Transfer {
-                        from: ctx.accounts.user_token_1.to_account_info(),
-                        to: ctx.accounts.pool_token_1.to_account_info(),
-                        authority: ctx.accounts.payer.to_account_info(),
-                    },
-                ),
-                amount_in_used,
+            if wrap_input {
+                wrap_native(
+                    &ctx.accounts.payer,
+                    &ctx.accounts.user_token_1,
+                    &ctx.accounts.token_program_1,
+                    &ctx.accounts.system_program,
+                    amount_in,
+                )?;
+            }
+
+            transfer_from_user_to_vault(
+                &ctx.accounts.payer,
+                &ctx.accounts.user_token_1,
+                &ctx.accounts.pool_token_1,
+                &ctx.accounts.token_mint_1,
+                &ctx.accounts.token_program_1,
+                ctx.remaining_accounts,
+                amount_in,
             )?;
 
-            token::transfer(
-                CpiContext::new_with_signer(
-                    ctx.accounts.token_program.to_account_info(),
-                    Transfer {
-                        from: ctx.accounts.pool_token_0.to_account_info(),
-                        to: ctx.accounts.user_token_0.to_account_info(),
-                        authority: pool.to_account_info(),
-                    },
-                    signer_seeds,
-                ),
+            transfer_from_vault_to_user(
+                pool,
+                &ctx.accounts.pool_token_0,
+                &ctx.accounts.recipient_token,
+                &ctx.accounts.token_mint_0,
+                &ctx.accounts.token_program_0,
+                ctx.remaining_accounts,
                 amount_out_calculated,
             )?;
+
+            if unwrap_output {
+                unwrap_native(
+                    &ctx.accounts.payer,
+                    &ctx.accounts.recipient_token,
+                    &ctx.accounts.token_program_0,
+                )?;
+            }
         }
 
-        pool.sqrt_price_x96 = new_sqrt_price_x96;
-        pool.current_tick = get_tick_at_sqrt_price(new_sqrt_price_x96)?;
+        pool.unlock();
+        pool.credit_swap_fee(fee_amount, swap_token_0_for_1)?;
+        let filled_limit_orders = move_price(
+            pool,
+            &tick_arrays,
+            new_sqrt_price_x96,
+            Clock::get()?.unix_timestamp as u32,
+            amount_in_received,
+        )?;
+
+        emit_cpi!(events::Swap {
+            pool: pool.key(),
+            sender: ctx.accounts.payer.key(),
+            swap_token_0_for_1,
+            amount_in,
+            amount_out: amount_out_calculated,
+            sqrt_price_x96_before,
+            sqrt_price_x96_after: pool.sqrt_price_x96,
+            tick_before,
+            tick_after: pool.current_tick,
+            liquidity: pool.global_liquidity,
+        });
+        for filled in filled_limit_orders {
+            emit_cpi!(filled);
+        }
 
-        Ok(amount_out_calculated)
+        Ok(amount_out_received)
     }
\ No newline at end of file
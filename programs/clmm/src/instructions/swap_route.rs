@@ -0,0 +1,213 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::{Mint, TokenAccount, TokenInterface};
+use crate::events;
+use crate::states::*;
+use crate::utils::ErrorCode;
+use crate::instructions::swap::{compute_swap, move_price, SwapComputation};
+use crate::utils::token::*;
+
+pub const MAX_ROUTE_HOPS: usize = 4;
+
+/// Remaining accounts per hop:
+/// `[pool, token_vault_0, token_vault_1, token_mint_0, token_mint_1, token_program_0, token_program_1]`.
+/// Transfer-hook extra accounts and the tick arrays every hop moves through
+/// follow the last hop, in any order.
+pub const ACCOUNTS_PER_HOP: usize = 7;
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct SwapRoute<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    #[account(
+        mut,
+        constraint = user_token_in.owner == payer.key() @ ErrorCode::InvalidTokenAccountOwner,
+    )]
+    pub user_token_in: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        constraint = user_token_out.owner == payer.key() @ ErrorCode::InvalidTokenAccountOwner,
+    )]
+    pub user_token_out: InterfaceAccount<'info, TokenAccount>,
+}
+
+struct Hop<'info> {
+    pool: Account<'info, Pool>,
+    vault_in: InterfaceAccount<'info, TokenAccount>,
+    vault_out: InterfaceAccount<'info, TokenAccount>,
+    mint_out: InterfaceAccount<'info, Mint>,
+    token_program_out: Interface<'info, TokenInterface>,
+    swap_token_0_for_1: bool,
+    amount_in: u64,
+    amount_in_received: u64,
+    amount_out: u64,
+    fee_amount: u64,
+    new_sqrt_price_x96: u128,
+}
+
+pub fn swap_route<'info>(
+    ctx: Context<'_, '_, 'info, 'info, SwapRoute<'info>>,
+    amount_in: u64,
+    swap_token_0_for_1: Vec<bool>,
+    amount_out_minimum: u64,
+) -> Result<u64> {
+    require!(amount_in > 0, ErrorCode::InsufficientInputAmount);
+    require!(
+        !swap_token_0_for_1.is_empty()
+            && swap_token_0_for_1.len() <= MAX_ROUTE_HOPS
+            && ctx.remaining_accounts.len() >= swap_token_0_for_1.len() * ACCOUNTS_PER_HOP,
+        ErrorCode::InvalidRoute
+    );
+
+    let mut hops: Vec<Hop<'info>> = Vec::with_capacity(swap_token_0_for_1.len());
+    let mut expected_mint_in = ctx.accounts.user_token_in.mint;
+    let mut transfer_in_mint: Option<InterfaceAccount<'info, Mint>> = None;
+    let mut transfer_in_program: Option<Interface<'info, TokenInterface>> = None;
+    let mut amount = amount_in;
+    let tick_arrays = &ctx.remaining_accounts[swap_token_0_for_1.len() * ACCOUNTS_PER_HOP..];
+
+    for (i, &zero_for_one) in swap_token_0_for_1.iter().enumerate() {
+        let accounts = &ctx.remaining_accounts[i * ACCOUNTS_PER_HOP..(i + 1) * ACCOUNTS_PER_HOP];
+
+        require!(accounts[0].is_writable, ErrorCode::InvalidRoute);
+        let pool = Account::<Pool>::try_from(&accounts[0])?;
+        require!(
+            hops.iter().all(|hop| hop.pool.key() != pool.key()),
+            ErrorCode::InvalidRoute
+        );
+        pool.require_not_paused(PAUSE_SWAP)?;
+
+        let vault_0 = InterfaceAccount::<TokenAccount>::try_from(&accounts[1])?;
+        let vault_1 = InterfaceAccount::<TokenAccount>::try_from(&accounts[2])?;
+        require_keys_eq!(vault_0.key(), pool.token_vault_0, ErrorCode::InvalidPoolVault);
+        require_keys_eq!(vault_1.key(), pool.token_vault_1, ErrorCode::InvalidPoolVault);
+
+        let mint_0 = InterfaceAccount::<Mint>::try_from(&accounts[3])?;
+        let mint_1 = InterfaceAccount::<Mint>::try_from(&accounts[4])?;
+        require_keys_eq!(mint_0.key(), pool.token_mint_0, ErrorCode::InvalidTokenMint);
+        require_keys_eq!(mint_1.key(), pool.token_mint_1, ErrorCode::InvalidTokenMint);
+
+        let token_program_0 = Interface::<TokenInterface>::try_from(&accounts[5])?;
+        let token_program_1 = Interface::<TokenInterface>::try_from(&accounts[6])?;
+
+        let (vault_in, vault_out, mint_in, mint_out, token_program_in, token_program_out) =
+            if zero_for_one {
+                (vault_0, vault_1, mint_0, mint_1, token_program_0, token_program_1)
+            } else {
+                (vault_1, vault_0, mint_1, mint_0, token_program_1, token_program_0)
+            };
+        require_keys_eq!(mint_in.key(), expected_mint_in, ErrorCode::InvalidRoute);
+
+        let SwapComputation {
+            amount_in_received,
+            amount_out,
+            fee_amount,
+            sqrt_price_x96_after: new_sqrt_price_x96,
+            ..
+        } = compute_swap(&pool, &mint_in, &mint_out, tick_arrays, amount, zero_for_one)?;
+
+        let hop_amount_in = amount;
+        if i == 0 {
+            transfer_in_mint = Some(mint_in);
+            transfer_in_program = Some(token_program_in);
+        }
+        expected_mint_in = mint_out.key();
+        amount = amount_out;
+
+        hops.push(Hop {
+            pool,
+            vault_in,
+            vault_out,
+            mint_out,
+            token_program_out,
+            swap_token_0_for_1: zero_for_one,
+            amount_in: hop_amount_in,
+            amount_in_received,
+            amount_out,
+            fee_amount,
+            new_sqrt_price_x96,
+        });
+    }
+
+    require_keys_eq!(ctx.accounts.user_token_out.mint, expected_mint_in, ErrorCode::InvalidTokenMint);
+
+    let last = hops.len() - 1;
+    let amount_out_received = hops[last]
+        .amount_out
+        .checked_sub(get_transfer_fee(&hops[last].mint_out, hops[last].amount_out)?)
+        .ok_or(ErrorCode::ArithmeticOverflow)?;
+    require!(
+        amount_out_received >= amount_out_minimum,
+        ErrorCode::SlippageExceeded
+    );
+
+    for hop in hops.iter_mut() {
+        hop.pool.lock()?;
+        hop.pool.exit(&crate::ID)?;
+    }
+
+    transfer_from_user_to_vault(
+        &ctx.accounts.payer,
+        &ctx.accounts.user_token_in,
+        &hops[0].vault_in,
+        transfer_in_mint.as_ref().ok_or(ErrorCode::InvalidRoute)?,
+        transfer_in_program.as_ref().ok_or(ErrorCode::InvalidRoute)?,
+        ctx.remaining_accounts,
+        amount_in,
+    )?;
+
+    // Each hop pays its output straight into the next pool's vault.
+    for i in 0..hops.len() {
+        let to = if i == last {
+            &ctx.accounts.user_token_out
+        } else {
+            &hops[i + 1].vault_in
+        };
+        transfer_from_vault_to_user(
+            &hops[i].pool,
+            &hops[i].vault_out,
+            to,
+            &hops[i].mint_out,
+            &hops[i].token_program_out,
+            ctx.remaining_accounts,
+            hops[i].amount_out,
+        )?;
+    }
+
+    let now = Clock::get()?.unix_timestamp as u32;
+    for hop in hops.iter_mut() {
+        let sqrt_price_x96_before = hop.pool.sqrt_price_x96;
+        let tick_before = hop.pool.current_tick;
+
+        hop.pool.unlock();
+        hop.pool.credit_swap_fee(hop.fee_amount, hop.swap_token_0_for_1)?;
+        let filled_limit_orders = move_price(
+            &mut hop.pool,
+            tick_arrays,
+            hop.new_sqrt_price_x96,
+            now,
+            hop.amount_in_received,
+        )?;
+        hop.pool.exit(&crate::ID)?;
+
+        emit_cpi!(events::Swap {
+            pool: hop.pool.key(),
+            sender: ctx.accounts.payer.key(),
+            swap_token_0_for_1: hop.swap_token_0_for_1,
+            amount_in: hop.amount_in,
+            amount_out: hop.amount_out,
+            sqrt_price_x96_before,
+            sqrt_price_x96_after: hop.pool.sqrt_price_x96,
+            tick_before,
+            tick_after: hop.pool.current_tick,
+            liquidity: hop.pool.global_liquidity,
+        });
+        for filled in filled_limit_orders {
+            emit_cpi!(filled);
+        }
+    }
+
+    Ok(amount_out_received)
+}
@@ -0,0 +1,83 @@
+use anchor_lang::prelude::*;
+use crate::events;
+use crate::states::*;
+use crate::utils::ErrorCode;
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct TransferPosition<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    /// CHECK: any account may receive a position; only its key is used.
+    #[account(constraint = new_owner.key() != owner.key() @ ErrorCode::InvalidPositionOwner)]
+    pub new_owner: UncheckedAccount<'info>,
+
+    pub pool: Account<'info, Pool>,
+
+    #[account(
+        mut,
+        close = owner,
+        seeds = [
+            b"position",
+            owner.key().as_ref(),
+            pool.key().as_ref(),
+            &position.tick_lower.to_le_bytes(),
+            &position.tick_upper.to_le_bytes(),
+        ],
+        bump = position.bump,
+        has_one = owner @ ErrorCode::InvalidPositionOwner,
+        has_one = pool @ ErrorCode::InvalidPositionRange,
+    )]
+    pub position: Account<'info, Position>,
+
+    #[account(
+        init,
+        payer = owner,
+        space = Position::SPACE,
+        seeds = [
+            b"position",
+            new_owner.key().as_ref(),
+            pool.key().as_ref(),
+            &position.tick_lower.to_le_bytes(),
+            &position.tick_upper.to_le_bytes(),
+        ],
+        bump,
+    )]
+    pub new_position: Account<'info, Position>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Re-creates the position under the new owner's seeds and closes the old
+/// one. Liquidity, range, accrued fees and any time lock carry over; the
+/// operator and keeper do not.
+pub fn transfer_position(ctx: Context<TransferPosition>) -> Result<()> {
+    let position = &ctx.accounts.position;
+    let new_position = &mut ctx.accounts.new_position;
+
+    new_position.liquidity = position.liquidity;
+    new_position.tick_lower = position.tick_lower;
+    new_position.tick_upper = position.tick_upper;
+    new_position.owner = ctx.accounts.new_owner.key();
+    new_position.pool = position.pool;
+    new_position.bump = ctx.bumps.new_position;
+    new_position.lock_until = position.lock_until;
+    new_position.operator = Pubkey::default();
+    new_position.fee_growth_inside_0_last_x64 = position.fee_growth_inside_0_last_x64;
+    new_position.fee_growth_inside_1_last_x64 = position.fee_growth_inside_1_last_x64;
+    new_position.tokens_owed_0 = position.tokens_owed_0;
+    new_position.tokens_owed_1 = position.tokens_owed_1;
+    new_position.keeper = Pubkey::default();
+
+    emit_cpi!(events::PositionTransferred {
+        pool: position.pool,
+        old_position: position.key(),
+        new_position: new_position.key(),
+        old_owner: position.owner,
+        new_owner: new_position.owner,
+        liquidity: new_position.liquidity,
+    });
+
+    Ok(())
+}
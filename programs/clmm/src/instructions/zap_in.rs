@@ -0,0 +1,308 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::{Mint, TokenAccount, TokenInterface};
+use crate::events;
+use crate::instructions::swap::{swap_in_vaults, VaultSwap};
+use crate::states::*;
+use crate::utils::ErrorCode;
+use crate::utils::math::*;
+use crate::utils::token::*;
+
+#[event_cpi]
+#[derive(Accounts)]
+#[instruction(lower_tick: i32, upper_tick: i32)]
+pub struct ZapIn<'info> {
+    #[account(
+        mut,
+        has_one = token_mint_0,
+        has_one = token_mint_1,
+    )]
+    pub pool: Account<'info, Pool>,
+
+    /// CHECK: tick array PDA holding `lower_tick`, created if needed and
+    /// updated in place since both arrays may be the same account.
+    #[account(
+        mut,
+        seeds = [
+            b"tick_array",
+            pool.key().as_ref(),
+            &TickArray::get_starting_tick_index(lower_tick, pool.tick_spacing).to_le_bytes()
+        ],
+        bump
+    )]
+    pub lower_tick_array: UncheckedAccount<'info>,
+
+    /// CHECK: tick array PDA holding `upper_tick`, created if needed and
+    /// updated in place since both arrays may be the same account.
+    #[account(
+        mut,
+        seeds = [
+            b"tick_array",
+            pool.key().as_ref(),
+            &TickArray::get_starting_tick_index(upper_tick, pool.tick_spacing).to_le_bytes()
+        ],
+        bump
+    )]
+    pub upper_tick_array: UncheckedAccount<'info>,
+
+    #[account(
+        init_if_needed,
+        payer = owner,
+        space = Position::SPACE,
+        seeds = [
+            b"position",
+            owner.key().as_ref(),
+            pool.key().as_ref(),
+            &lower_tick.to_le_bytes(),
+            &upper_tick.to_le_bytes(),
+        ],
+        bump,
+    )]
+    pub position: Account<'info, Position>,
+
+    #[account(
+        mut,
+        constraint = user_token_0.mint == pool.token_mint_0 @ ErrorCode::InvalidTokenMint,
+        constraint = user_token_0.owner == owner.key() @ ErrorCode::InvalidTokenAccountOwner,
+    )]
+    pub user_token_0: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        constraint = user_token_1.mint == pool.token_mint_1 @ ErrorCode::InvalidTokenMint,
+        constraint = user_token_1.owner == owner.key() @ ErrorCode::InvalidTokenAccountOwner,
+    )]
+    pub user_token_1: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        address = pool.token_vault_0 @ ErrorCode::InvalidPoolVault,
+    )]
+    pub pool_token_0: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        address = pool.token_vault_1 @ ErrorCode::InvalidPoolVault,
+    )]
+    pub pool_token_1: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    pub token_mint_0: InterfaceAccount<'info, Mint>,
+    pub token_mint_1: InterfaceAccount<'info, Mint>,
+    pub system_program: Program<'info, System>,
+    pub token_program_0: Interface<'info, TokenInterface>,
+    pub token_program_1: Interface<'info, TokenInterface>,
+}
+
+/// Deposits `amount_in` of a single token into a range: part of it is swapped
+/// through this pool to match the range's ratio, the rest is minted as
+/// liquidity, and anything the range cannot use is refunded.
+pub fn zap_in<'info>(
+    ctx: Context<'_, '_, 'info, 'info, ZapIn<'info>>,
+    lower_tick: i32,
+    upper_tick: i32,
+    amount_in: u64,
+    input_is_token_0: bool,
+    min_liquidity: u128,
+) -> Result<u128> {
+    let pool = &mut ctx.accounts.pool;
+    pool.require_not_paused(PAUSE_DEPOSIT)?;
+
+    require!(
+        lower_tick < upper_tick
+            && lower_tick >= MIN_TICK
+            && upper_tick <= MAX_TICK
+            && lower_tick % pool.tick_spacing == 0
+            && upper_tick % pool.tick_spacing == 0,
+        ErrorCode::InvalidTickRange
+    );
+    require!(amount_in > 0, ErrorCode::InsufficientInputAmount);
+
+    let now = Clock::get()?.unix_timestamp as u32;
+    let input_mint = if input_is_token_0 {
+        &ctx.accounts.token_mint_0
+    } else {
+        &ctx.accounts.token_mint_1
+    };
+
+    // Only what reaches the vault after transfer fees is zapped.
+    let amount_received = amount_in
+        .checked_sub(get_transfer_fee(input_mint, amount_in)?)
+        .ok_or(ErrorCode::ArithmeticOverflow)?;
+
+    let lower_sqrt_price_x96 = get_sqrt_price_from_tick(lower_tick)?;
+    let upper_sqrt_price_x96 = get_sqrt_price_from_tick(upper_tick)?;
+    let swap_amount = get_zap_swap_amount(
+        pool.sqrt_price_x96,
+        lower_sqrt_price_x96,
+        upper_sqrt_price_x96,
+        amount_received,
+        input_is_token_0,
+        pool.fee_rate(now),
+    )?;
+
+    let mut swap_events = None;
+    let mut swap_amount_out = 0;
+    if swap_amount > 0 {
+        let tick_arrays: Vec<AccountInfo<'info>> = [
+            ctx.accounts.lower_tick_array.to_account_info(),
+            ctx.accounts.upper_tick_array.to_account_info(),
+        ]
+        .into_iter()
+        .chain(ctx.remaining_accounts.iter().cloned())
+        .collect();
+        let VaultSwap {
+            amount_out,
+            swap_event,
+            filled_limit_orders,
+        } = swap_in_vaults(
+            pool,
+            ctx.accounts.owner.key(),
+            &tick_arrays,
+            swap_amount,
+            input_is_token_0,
+            now,
+        )?;
+        swap_amount_out = amount_out;
+        swap_events = Some((swap_event, filled_limit_orders));
+    }
+
+    let amount_kept = amount_received - swap_amount;
+    let (amount_0, amount_1) = if input_is_token_0 {
+        (amount_kept, swap_amount_out)
+    } else {
+        (swap_amount_out, amount_kept)
+    };
+
+    let liquidity = get_liquidity_for_amounts(
+        pool.sqrt_price_x96,
+        lower_sqrt_price_x96,
+        upper_sqrt_price_x96,
+        amount_0,
+        amount_1,
+    )?;
+    require!(
+        liquidity > 0 && liquidity >= min_liquidity,
+        ErrorCode::SlippageExceeded
+    );
+    let (used_0, used_1) = get_amounts_for_liquidity(
+        pool.sqrt_price_x96,
+        lower_sqrt_price_x96,
+        upper_sqrt_price_x96,
+        liquidity,
+    )?;
+
+    for (tick_array, tick) in [
+        (&ctx.accounts.lower_tick_array, lower_tick),
+        (&ctx.accounts.upper_tick_array, upper_tick),
+    ] {
+        TickArray::init_if_needed(
+            &ctx.accounts.owner,
+            &ctx.accounts.system_program,
+            tick_array,
+            pool.key(),
+            tick,
+            pool.tick_spacing,
+        )?;
+    }
+    let (fee_growth_inside_0_x64, fee_growth_inside_1_x64) = TickArray::update_position_ticks_in_place(
+        &ctx.accounts.lower_tick_array,
+        &ctx.accounts.upper_tick_array,
+        pool,
+        lower_tick,
+        upper_tick,
+        liquidity as i128,
+    )?;
+
+    let owner = ctx.accounts.owner.key();
+    let position = &mut ctx.accounts.position;
+    position.accrue_fees(fee_growth_inside_0_x64, fee_growth_inside_1_x64)?;
+    if position.liquidity == 0 && position.owner == Pubkey::default() {
+        position.owner = owner;
+        position.pool = pool.key();
+        position.tick_lower = lower_tick;
+        position.tick_upper = upper_tick;
+        position.liquidity = liquidity;
+        position.bump = ctx.bumps.position;
+    } else {
+        require!(position.owner == owner, ErrorCode::InvalidPositionOwner);
+        position.liquidity = position
+            .liquidity
+            .checked_add(liquidity)
+            .ok_or(ErrorCode::ArithmeticOverflow)?;
+    }
+
+    pool.write_observation(now)?;
+    pool.apply_liquidity_delta(lower_tick, upper_tick, liquidity as i128)?;
+
+    let refund_0 = amount_0.checked_sub(used_0).ok_or(ErrorCode::ArithmeticOverflow)?;
+    let refund_1 = amount_1.checked_sub(used_1).ok_or(ErrorCode::ArithmeticOverflow)?;
+
+    pool.lock()?;
+    pool.exit(&crate::ID)?;
+
+    if input_is_token_0 {
+        transfer_from_user_to_vault(
+            &ctx.accounts.owner,
+            &ctx.accounts.user_token_0,
+            &ctx.accounts.pool_token_0,
+            &ctx.accounts.token_mint_0,
+            &ctx.accounts.token_program_0,
+            ctx.remaining_accounts,
+            amount_in,
+        )?;
+    } else {
+        transfer_from_user_to_vault(
+            &ctx.accounts.owner,
+            &ctx.accounts.user_token_1,
+            &ctx.accounts.pool_token_1,
+            &ctx.accounts.token_mint_1,
+            &ctx.accounts.token_program_1,
+            ctx.remaining_accounts,
+            amount_in,
+        )?;
+    }
+
+    if refund_0 > 0 {
+        transfer_from_vault_to_user(
+            pool,
+            &ctx.accounts.pool_token_0,
+            &ctx.accounts.user_token_0,
+            &ctx.accounts.token_mint_0,
+            &ctx.accounts.token_program_0,
+            ctx.remaining_accounts,
+            refund_0,
+        )?;
+    }
+
+    if refund_1 > 0 {
+        transfer_from_vault_to_user(
+            pool,
+            &ctx.accounts.pool_token_1,
+            &ctx.accounts.user_token_1,
+            &ctx.accounts.token_mint_1,
+            &ctx.accounts.token_program_1,
+            ctx.remaining_accounts,
+            refund_1,
+        )?;
+    }
+
+    pool.unlock();
+
+    if let Some((swap_event, filled_limit_orders)) = swap_events {
+        emit_cpi!(swap_event);
+        for filled in filled_limit_orders {
+            emit_cpi!(filled);
+        }
+    }
+    emit_cpi!(events::LiquidityIncreased {
+        pool: pool.key(),
+        position: position.key(),
+        owner,
+        tick_lower: lower_tick,
+        tick_upper: upper_tick,
+        liquidity,
+        amount_0: used_0,
+        amount_1: used_1,
+    });
+
+    Ok(liquidity)
+}
@@ -0,0 +1,246 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::{Mint, TokenAccount, TokenInterface};
+use crate::events;
+use crate::instructions::swap::{swap_in_vaults, VaultSwap};
+use crate::states::*;
+use crate::utils::ErrorCode;
+use crate::utils::math::*;
+use crate::utils::token::*;
+
+#[event_cpi]
+#[derive(Accounts)]
+#[instruction(lower_tick: i32, upper_tick: i32)]
+pub struct ZapOut<'info> {
+    #[account(
+        mut,
+        has_one = token_mint_0,
+        has_one = token_mint_1,
+    )]
+    pub pool: Account<'info, Pool>,
+
+    #[account(
+        mut,
+        seeds = [
+            b"tick_array",
+            pool.key().as_ref(),
+            &TickArray::get_starting_tick_index(lower_tick, pool.tick_spacing).to_le_bytes()
+        ],
+        bump = lower_tick_array.bump,
+        constraint = lower_tick_array.pool == pool.key() @ ErrorCode::InvalidTickArrayPool,
+    )]
+    pub lower_tick_array: Account<'info, TickArray>,
+
+    #[account(
+        mut,
+        seeds = [
+            b"tick_array",
+            pool.key().as_ref(),
+            &TickArray::get_starting_tick_index(upper_tick, pool.tick_spacing).to_le_bytes()
+        ],
+        bump = upper_tick_array.bump,
+        constraint = upper_tick_array.pool == pool.key() @ ErrorCode::InvalidTickArrayPool,
+    )]
+    pub upper_tick_array: Account<'info, TickArray>,
+
+    #[account(
+        mut,
+        seeds = [
+            b"position",
+            position.owner.as_ref(),
+            pool.key().as_ref(),
+            &lower_tick.to_le_bytes(),
+            &upper_tick.to_le_bytes(),
+        ],
+        bump = position.bump,
+        constraint = position.pool == pool.key() @ ErrorCode::InvalidPositionRange,
+        constraint = position.is_authorized(&payer.key()) @ ErrorCode::InvalidPositionOwner,
+    )]
+    pub position: Account<'info, Position>,
+
+    // Proceeds always go to the position owner, even when an operator signs.
+    #[account(
+        mut,
+        constraint = user_token_0.mint == pool.token_mint_0 @ ErrorCode::InvalidTokenMint,
+        constraint = user_token_0.owner == position.owner @ ErrorCode::InvalidTokenAccountOwner,
+    )]
+    pub user_token_0: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        constraint = user_token_1.mint == pool.token_mint_1 @ ErrorCode::InvalidTokenMint,
+        constraint = user_token_1.owner == position.owner @ ErrorCode::InvalidTokenAccountOwner,
+    )]
+    pub user_token_1: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        address = pool.token_vault_0 @ ErrorCode::InvalidPoolVault,
+    )]
+    pub pool_token_0: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        address = pool.token_vault_1 @ ErrorCode::InvalidPoolVault,
+    )]
+    pub pool_token_1: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub token_mint_0: InterfaceAccount<'info, Mint>,
+    pub token_mint_1: InterfaceAccount<'info, Mint>,
+    pub token_program_0: Interface<'info, TokenInterface>,
+    pub token_program_1: Interface<'info, TokenInterface>,
+}
+
+/// Removes `liquidity_amount` from the position and swaps the withdrawn
+/// amount of the other token through this pool, paying out only token 0 or
+/// token 1. Returns the amount the owner receives after transfer fees.
+pub fn zap_out<'info>(
+    ctx: Context<'_, '_, 'info, 'info, ZapOut<'info>>,
+    lower_tick: i32,
+    upper_tick: i32,
+    liquidity_amount: u128,
+    output_is_token_0: bool,
+    amount_out_minimum: u64,
+) -> Result<u64> {
+    let pool = &mut ctx.accounts.pool;
+    pool.require_not_paused(PAUSE_WITHDRAW)?;
+    let now = Clock::get()?.unix_timestamp;
+    let position = &mut ctx.accounts.position;
+    position.require_unlocked(now)?;
+
+    require!(
+        lower_tick < upper_tick
+            && lower_tick % pool.tick_spacing == 0
+            && upper_tick % pool.tick_spacing == 0,
+        ErrorCode::InvalidTickRange
+    );
+    require!(liquidity_amount > 0, ErrorCode::InsufficientInputAmount);
+
+    let (fee_growth_inside_0_x64, fee_growth_inside_1_x64) = TickArray::update_position_ticks(
+        &mut ctx.accounts.lower_tick_array,
+        &mut ctx.accounts.upper_tick_array,
+        pool,
+        lower_tick,
+        upper_tick,
+        -(liquidity_amount as i128),
+    )?;
+
+    position.accrue_fees(fee_growth_inside_0_x64, fee_growth_inside_1_x64)?;
+    position.liquidity = position
+        .liquidity
+        .checked_sub(liquidity_amount)
+        .ok_or(ErrorCode::InsufficientLiquidity)?;
+
+    let (amount_0, amount_1) = get_amounts_for_liquidity(
+        pool.sqrt_price_x96,
+        get_sqrt_price_from_tick(lower_tick)?,
+        get_sqrt_price_from_tick(upper_tick)?,
+        liquidity_amount,
+    )?;
+
+    pool.write_observation(now as u32)?;
+    pool.apply_liquidity_delta(lower_tick, upper_tick, -(liquidity_amount as i128))?;
+
+    // The withdrawn amount of the other token is swapped against the
+    // liquidity that remains after this withdrawal.
+    let (amount_kept, amount_to_swap) = if output_is_token_0 {
+        (amount_0, amount_1)
+    } else {
+        (amount_1, amount_0)
+    };
+
+    let mut swap_events = None;
+    let mut swap_amount_out = 0;
+    if amount_to_swap > 0 {
+        // The swap may cross ticks in the position's own arrays, which are
+        // written back first and reloaded after.
+        ctx.accounts.lower_tick_array.exit(&crate::ID)?;
+        ctx.accounts.upper_tick_array.exit(&crate::ID)?;
+        let tick_arrays: Vec<AccountInfo<'info>> = [
+            ctx.accounts.lower_tick_array.to_account_info(),
+            ctx.accounts.upper_tick_array.to_account_info(),
+        ]
+        .into_iter()
+        .chain(ctx.remaining_accounts.iter().cloned())
+        .collect();
+        let VaultSwap {
+            amount_out,
+            swap_event,
+            filled_limit_orders,
+        } = swap_in_vaults(
+            pool,
+            ctx.accounts.payer.key(),
+            &tick_arrays,
+            amount_to_swap,
+            !output_is_token_0,
+            now as u32,
+        )?;
+        ctx.accounts.lower_tick_array.reload()?;
+        ctx.accounts.upper_tick_array.reload()?;
+        swap_amount_out = amount_out;
+        swap_events = Some((swap_event, filled_limit_orders));
+    }
+
+    let amount_out = amount_kept
+        .checked_add(swap_amount_out)
+        .ok_or(ErrorCode::ArithmeticOverflow)?;
+    let output_mint = if output_is_token_0 {
+        &ctx.accounts.token_mint_0
+    } else {
+        &ctx.accounts.token_mint_1
+    };
+    let amount_out_received = amount_out
+        .checked_sub(get_transfer_fee(output_mint, amount_out)?)
+        .ok_or(ErrorCode::ArithmeticOverflow)?;
+    require!(
+        amount_out_received >= amount_out_minimum,
+        ErrorCode::SlippageExceeded
+    );
+
+    pool.lock()?;
+    pool.exit(&crate::ID)?;
+
+    if amount_out > 0 {
+        if output_is_token_0 {
+            transfer_from_vault_to_user(
+                pool,
+                &ctx.accounts.pool_token_0,
+                &ctx.accounts.user_token_0,
+                &ctx.accounts.token_mint_0,
+                &ctx.accounts.token_program_0,
+                ctx.remaining_accounts,
+                amount_out,
+            )?;
+        } else {
+            transfer_from_vault_to_user(
+                pool,
+                &ctx.accounts.pool_token_1,
+                &ctx.accounts.user_token_1,
+                &ctx.accounts.token_mint_1,
+                &ctx.accounts.token_program_1,
+                ctx.remaining_accounts,
+                amount_out,
+            )?;
+        }
+    }
+
+    pool.unlock();
+
+    emit_cpi!(events::LiquidityDecreased {
+        pool: pool.key(),
+        position: position.key(),
+        owner: position.owner,
+        tick_lower: lower_tick,
+        tick_upper: upper_tick,
+        liquidity: liquidity_amount,
+        amount_0,
+        amount_1,
+    });
+    if let Some((swap_event, filled_limit_orders)) = swap_events {
+        emit_cpi!(swap_event);
+        for filled in filled_limit_orders {
+            emit_cpi!(filled);
+        }
+    }
+
+    Ok(amount_out_received)
+}
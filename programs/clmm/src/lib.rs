@@ -1,6 +1,9 @@
+#![allow(clippy::too_many_arguments)]
+
 pub mod states;
 pub mod utils;
 pub mod instructions;
+pub mod events;
 
 use crate::instructions::*;
 use anchor_lang::prelude::*;
@@ -11,6 +14,21 @@ declare_id!("4GhrgMYusqS5uuyzrrBvFv3FuVGp4RRp4XKDBctyW6oN");
 pub mod clmm {
     use super::*;
 
+    pub fn initialize_config(ctx: Context<InitializeConfig>) -> Result<()> {
+        instructions::config::initialize_config(ctx)
+    }
+
+    pub fn set_trusted_transfer_hook_programs(
+        ctx: Context<UpdateConfig>,
+        programs: Vec<Pubkey>,
+    ) -> Result<()> {
+        instructions::config::set_trusted_transfer_hook_programs(ctx, programs)
+    }
+
+    pub fn set_allowed_freezable_mints(ctx: Context<UpdateConfig>, mints: Vec<Pubkey>) -> Result<()> {
+        instructions::config::set_allowed_freezable_mints(ctx, mints)
+    }
+
     pub fn initialize_pool(
         ctx: Context<InitializePool>,
         tick_spacing: i32,
@@ -19,21 +37,148 @@ pub mod clmm {
         instructions::pool::init_pool(ctx, tick_spacing, initial_sqrt_price)
     }
 
-    pub fn open_position(
-        ctx: Context<OpenPosition>, 
+    pub fn set_pause_flags(ctx: Context<SetPauseFlags>, pause_flags: u8) -> Result<()> {
+        instructions::pool::set_pause_flags(ctx, pause_flags)
+    }
+
+    pub fn set_dynamic_fee(
+        ctx: Context<SetDynamicFee>,
+        enabled: bool,
+        min_fee_rate: u32,
+        max_fee_rate: u32,
+    ) -> Result<()> {
+        instructions::pool::set_dynamic_fee(ctx, enabled, min_fee_rate, max_fee_rate)
+    }
+
+    pub fn open_position<'info>(
+        ctx: Context<'_, '_, '_, 'info, OpenPosition<'info>>, 
         owner: Pubkey,
         lower_tick: i32, 
         upper_tick: i32, 
         liquidity_amount: u128,
         _tick_array_lower_start_index: i32,
-        _tick_array_upper_start_index: i32
+        _tick_array_upper_start_index: i32,
+        native_sol: bool,
+        lock_until: i64,
+    ) -> Result<(u64, u64)> {
+       instructions::open_position::open_position(ctx, owner, lower_tick, upper_tick, liquidity_amount, _tick_array_lower_start_index,_tick_array_upper_start_index, native_sol, lock_until)
+    }
+
+    pub fn lock_position(ctx: Context<LockPosition>, lock_until: i64) -> Result<()> {
+        instructions::lock_position::lock_position(ctx, lock_until)
+    }
+
+    pub fn collect_fees<'info>(
+        ctx: Context<'_, '_, '_, 'info, CollectFees<'info>>,
+    ) -> Result<(u64, u64)> {
+        instructions::collect_fees::collect_fees(ctx)
+    }
+
+    pub fn set_position_operator(ctx: Context<SetPositionOperator>, operator: Pubkey) -> Result<()> {
+        instructions::set_position_operator::set_position_operator(ctx, operator)
+    }
+
+    pub fn set_position_keeper(ctx: Context<SetPositionKeeper>, keeper: Pubkey) -> Result<()> {
+        instructions::set_position_keeper::set_position_keeper(ctx, keeper)
+    }
+
+    pub fn transfer_position(ctx: Context<TransferPosition>) -> Result<()> {
+        instructions::transfer_position::transfer_position(ctx)
+    }
+
+    pub fn split_position(ctx: Context<SplitPosition>, liquidity: u128) -> Result<()> {
+        instructions::split_position::split_position(ctx, liquidity)
+    }
+
+    pub fn merge_positions(ctx: Context<MergePositions>) -> Result<()> {
+        instructions::merge_positions::merge_positions(ctx)
+    }
+
+    pub fn initialize_position_bundle(ctx: Context<InitializePositionBundle>) -> Result<()> {
+        instructions::position_bundle::initialize_position_bundle(ctx)
+    }
+
+    pub fn close_position_bundle(ctx: Context<ClosePositionBundle>) -> Result<()> {
+        instructions::position_bundle::close_position_bundle(ctx)
+    }
+
+    pub fn open_bundled_position<'info>(
+        ctx: Context<'_, '_, '_, 'info, OpenBundledPosition<'info>>,
+        slot: u8,
+        lower_tick: i32,
+        upper_tick: i32,
+        liquidity_amount: u128,
+    ) -> Result<(u64, u64)> {
+        instructions::position_bundle::open_bundled_position(ctx, slot, lower_tick, upper_tick, liquidity_amount)
+    }
+
+    pub fn increase_bundled_position<'info>(
+        ctx: Context<'_, '_, '_, 'info, ModifyBundledPosition<'info>>,
+        slot: u8,
+        lower_tick: i32,
+        upper_tick: i32,
+        liquidity_amount: u128,
+    ) -> Result<(u64, u64)> {
+        instructions::position_bundle::increase_bundled_position(ctx, slot, lower_tick, upper_tick, liquidity_amount)
+    }
+
+    pub fn decrease_bundled_position<'info>(
+        ctx: Context<'_, '_, '_, 'info, ModifyBundledPosition<'info>>,
+        slot: u8,
+        lower_tick: i32,
+        upper_tick: i32,
+        liquidity_amount: u128,
+    ) -> Result<(u64, u64)> {
+        instructions::position_bundle::decrease_bundled_position(ctx, slot, lower_tick, upper_tick, liquidity_amount)
+    }
 
+    pub fn close_bundled_position<'info>(
+        ctx: Context<'_, '_, '_, 'info, ModifyBundledPosition<'info>>,
+        slot: u8,
+        lower_tick: i32,
+        upper_tick: i32,
     ) -> Result<(u64, u64)> {
-       instructions::open_position::open_position(ctx, owner, lower_tick, upper_tick, liquidity_amount, _tick_array_lower_start_index,_tick_array_upper_start_index)
+        instructions::position_bundle::close_bundled_position(ctx, slot, lower_tick, upper_tick)
     }
 
-    pub fn increase_liquidity(
-        ctx: Context<IncreaseLiquidity>,
+    pub fn batch_modify_liquidity<'info>(
+        ctx: Context<'_, '_, 'info, 'info, BatchModifyLiquidity<'info>>,
+        entries: Vec<LiquidityEntry>,
+    ) -> Result<(i128, i128)> {
+        instructions::batch_modify_liquidity::batch_modify_liquidity(ctx, entries)
+    }
+
+    pub fn zap_in<'info>(
+        ctx: Context<'_, '_, 'info, 'info, ZapIn<'info>>,
+        lower_tick: i32,
+        upper_tick: i32,
+        amount_in: u64,
+        input_is_token_0: bool,
+        min_liquidity: u128,
+    ) -> Result<u128> {
+        instructions::zap_in::zap_in(ctx, lower_tick, upper_tick, amount_in, input_is_token_0, min_liquidity)
+    }
+
+    pub fn zap_out<'info>(
+        ctx: Context<'_, '_, 'info, 'info, ZapOut<'info>>,
+        lower_tick: i32,
+        upper_tick: i32,
+        liquidity_amount: u128,
+        output_is_token_0: bool,
+        amount_out_minimum: u64,
+    ) -> Result<u64> {
+        instructions::zap_out::zap_out(ctx, lower_tick, upper_tick, liquidity_amount, output_is_token_0, amount_out_minimum)
+    }
+
+    pub fn compound_position<'info>(
+        ctx: Context<'_, '_, 'info, 'info, CompoundPosition<'info>>,
+        min_liquidity: u128,
+    ) -> Result<u128> {
+        instructions::compound_position::compound_position(ctx, min_liquidity)
+    }
+
+    pub fn increase_liquidity<'info>(
+        ctx: Context<'_, '_, '_, 'info, IncreaseLiquidity<'info>>,
         lower_tick: i32,
         upper_tick: i32,
         liquidity_amount: u128
@@ -41,8 +186,8 @@ pub mod clmm {
         instructions::increase_liquidity::increase_liquidity(ctx, liquidity_amount, lower_tick, upper_tick)
     }
 
-    pub fn decrease_liquiduty(
-        ctx: Context<DecreaseLiquidity>,
+    pub fn decrease_liquiduty<'info>(
+        ctx: Context<'_, '_, '_, 'info, DecreaseLiquidity<'info>>,
         lower_tick: i32,
         upper_tick: i32,
         liquidity_amount: u128
@@ -50,8 +195,88 @@ pub mod clmm {
         instructions::decrease_liquidity::decrease_liquidity(ctx, liquidity_amount, lower_tick, upper_tick)
     }
 
-    pub fn swap(ctx: Context<Swap>, amount_in: u64, swap_token_0_for_1: bool, amount_out_minimum: u64) -> Result<u64> {
-        instructions::swap::swap(ctx, amount_in, swap_token_0_for_1, amount_out_minimum)    
+    pub fn close_position<'info>(
+        ctx: Context<'_, '_, '_, 'info, ClosePosition<'info>>,
+        lower_tick: i32,
+        upper_tick: i32,
+    ) -> Result<(u64, u64)> {
+        instructions::close_positon::close_position(ctx, lower_tick, upper_tick)
+    }
+
+    pub fn swap<'info>(ctx: Context<'_, '_, 'info, 'info, Swap<'info>>, amount_in: u64, swap_token_0_for_1: bool, amount_out_minimum: u64, native_sol: bool) -> Result<u64> {
+        instructions::swap::swap(ctx, amount_in, swap_token_0_for_1, amount_out_minimum, native_sol)    
+    }
+
+    pub fn swap_route<'info>(
+        ctx: Context<'_, '_, 'info, 'info, SwapRoute<'info>>,
+        amount_in: u64,
+        swap_token_0_for_1: Vec<bool>,
+        amount_out_minimum: u64,
+    ) -> Result<u64> {
+        instructions::swap_route::swap_route(ctx, amount_in, swap_token_0_for_1, amount_out_minimum)
+    }
+
+    pub fn flash<'info>(
+        ctx: Context<'_, '_, '_, 'info, Flash<'info>>,
+        amount_0: u64,
+        amount_1: u64,
+        data: Vec<u8>,
+    ) -> Result<()> {
+        instructions::flash::flash(ctx, amount_0, amount_1, data)
+    }
+
+    pub fn observe(ctx: Context<Observe>, seconds_agos: Vec<u32>) -> Result<ObserveResult> {
+        instructions::observe::observe(ctx, seconds_agos)
+    }
+
+    pub fn quote_swap<'info>(
+        ctx: Context<'_, '_, 'info, 'info, QuoteSwap<'info>>,
+        amount_in: u64,
+        swap_token_0_for_1: bool,
+    ) -> Result<SwapQuote> {
+        instructions::quote_swap::quote_swap(ctx, amount_in, swap_token_0_for_1)
+    }
+
+    pub fn place_limit_order<'info>(
+        ctx: Context<'_, '_, '_, 'info, PlaceLimitOrder<'info>>,
+        tick: i32,
+        amount: u64,
+    ) -> Result<()> {
+        instructions::limit_order::place_limit_order(ctx, tick, amount)
+    }
+
+    pub fn claim_filled_order<'info>(
+        ctx: Context<'_, '_, '_, 'info, ClaimFilledOrder<'info>>,
+    ) -> Result<u64> {
+        instructions::limit_order::claim_filled_order(ctx)
+    }
+
+    pub fn cancel_limit_order<'info>(
+        ctx: Context<'_, '_, '_, 'info, CancelLimitOrder<'info>>,
+    ) -> Result<u64> {
+        instructions::limit_order::cancel_limit_order(ctx)
+    }
+
+    pub fn rebalance_position<'info>(
+        ctx: Context<'_, '_, 'info, 'info, RebalancePosition<'info>>,
+        old_lower_tick: i32,
+        old_upper_tick: i32,
+        new_lower_tick: i32,
+        new_upper_tick: i32,
+        swap_amount_in: u64,
+        swap_token_0_for_1: bool,
+        min_liquidity: u128,
+    ) -> Result<u128> {
+        instructions::rebalance_position::rebalance_position(
+            ctx,
+            old_lower_tick,
+            old_upper_tick,
+            new_lower_tick,
+            new_upper_tick,
+            swap_amount_in,
+            swap_token_0_for_1,
+            min_liquidity,
+        )
     }
 
 }
@@ -0,0 +1,28 @@
+use anchor_lang::prelude::*;
+
+pub const MAX_TRUSTED_TRANSFER_HOOK_PROGRAMS: usize = 8;
+pub const MAX_ALLOWED_FREEZABLE_MINTS: usize = 16;
+
+#[account]
+pub struct Config {
+    pub authority: Pubkey,
+    pub trusted_transfer_hook_programs: Vec<Pubkey>,
+    pub allowed_freezable_mints: Vec<Pubkey>,
+    pub bump: u8,
+}
+
+impl Config {
+    pub const SPACE: usize = 8 + // discriminator
+        32 + // authority
+        4 + MAX_TRUSTED_TRANSFER_HOOK_PROGRAMS * 32 + // trusted_transfer_hook_programs
+        4 + MAX_ALLOWED_FREEZABLE_MINTS * 32 + // allowed_freezable_mints
+        1;   // bump
+
+    pub fn is_trusted_transfer_hook_program(&self, program_id: &Pubkey) -> bool {
+        self.trusted_transfer_hook_programs.contains(program_id)
+    }
+
+    pub fn is_freezable_mint_allowed(&self, mint: &Pubkey) -> bool {
+        self.allowed_freezable_mints.contains(mint)
+    }
+}
@@ -0,0 +1,25 @@
+use anchor_lang::prelude::*;
+
+/// One owner's order resting on a tick. It is filled once the tick's
+/// `limit_order_epoch` has moved past `epoch`.
+#[account]
+pub struct LimitOrder {
+    pub owner: Pubkey,
+    pub pool: Pubkey,
+    pub tick: i32,
+    pub zero_for_one: bool,
+    pub epoch: u64,
+    pub amount_in: u64,
+    pub bump: u8,
+}
+
+impl LimitOrder {
+    pub const SPACE: usize = 8 + // discriminator
+        32 + // owner
+        32 + // pool
+        4 +  // tick
+        1 +  // zero_for_one
+        8 +  // epoch
+        8 +  // amount_in
+        1;   // bump
+}
@@ -1,7 +1,15 @@
 pub mod pool;
 pub mod position;
 pub mod tick;
+pub mod oracle;
+pub mod config;
+pub mod limit_order;
+pub mod position_bundle;
 
 pub use pool::*;
 pub use position::*;
-pub use tick::*;
\ No newline at end of file
+pub use tick::*;
+pub use oracle::*;
+pub use config::*;
+pub use limit_order::*;
+pub use position_bundle::*;
\ No newline at end of file
@@ -0,0 +1,37 @@
+use anchor_lang::prelude::*;
+
+pub const OBSERVATION_CAPACITY: usize = 16;
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default, InitSpace)]
+pub struct Observation {
+    pub block_timestamp: u32,
+    pub tick_cumulative: i64,
+    pub seconds_per_liquidity_cumulative_x64: u128,
+    pub initialized: bool,
+}
+
+impl Observation {
+
+    pub const SPACE: usize =
+        4 +  // block_timestamp
+        8 +  // tick_cumulative
+        16 + // seconds_per_liquidity_cumulative_x64
+        1;   // initialized
+
+    /// Rolls this observation forward to `block_timestamp`, assuming the pool
+    /// sat at `tick` with `liquidity` in range for the whole interval.
+    /// Cumulatives are allowed to wrap; only differences between them are meaningful.
+    pub fn transform(&self, block_timestamp: u32, tick: i32, liquidity: u128) -> Observation {
+        let delta = block_timestamp.saturating_sub(self.block_timestamp);
+        Observation {
+            block_timestamp,
+            tick_cumulative: self
+                .tick_cumulative
+                .wrapping_add((tick as i64).wrapping_mul(delta as i64)),
+            seconds_per_liquidity_cumulative_x64: self
+                .seconds_per_liquidity_cumulative_x64
+                .wrapping_add(((delta as u128) << 64) / liquidity.max(1)),
+            initialized: true,
+        }
+    }
+}
@@ -1,4 +1,23 @@
 use anchor_lang::prelude::*;
+use crate::states::oracle::*;
+use crate::utils::ErrorCode;
+use crate::utils::math::{get_tick_at_sqrt_price, DEFAULT_FEE_RATE};
+
+pub const POOL_STATUS_UNLOCKED: u8 = 0;
+pub const POOL_STATUS_LOCKED: u8 = 1;
+
+pub const PAUSE_SWAP: u8 = 1 << 0;
+pub const PAUSE_DEPOSIT: u8 = 1 << 1;
+pub const PAUSE_WITHDRAW: u8 = 1 << 2;
+pub const PAUSE_COLLECT_FEES: u8 = 1 << 3;
+pub const PAUSE_FLASH: u8 = 1 << 4;
+pub const PAUSE_ALL: u8 =
+    PAUSE_SWAP | PAUSE_DEPOSIT | PAUSE_WITHDRAW | PAUSE_COLLECT_FEES | PAUSE_FLASH;
+
+/// Seconds over which the volatility accumulator decays linearly back to zero.
+pub const VOLATILITY_DECAY_PERIOD: u32 = 600;
+/// Fee rate added for every tick of accumulated volatility in dynamic-fee mode.
+pub const VOLATILITY_FEE_RATE_PER_TICK: u64 = 1;
 
 #[account]
 #[derive(InitSpace)]
@@ -7,11 +26,26 @@ pub struct Pool {
     pub token_mint_1: Pubkey,
     pub token_vault_0: Pubkey,
     pub token_vault_1: Pubkey,
+    /// Liquidity of the positions whose range holds the current price.
     pub global_liquidity: u128,
     pub sqrt_price_x96: u128,
+    pub fee_growth_global_0_x64: u128,
+    pub fee_growth_global_1_x64: u128,
     pub current_tick: i32,
     pub tick_spacing: i32,
+    pub mint_decimals_0: u8,
+    pub mint_decimals_1: u8,
     pub bump: u8,
+    pub status: u8,
+    pub pause_flags: u8,
+    pub observation_index: u16,
+    pub observation_cardinality: u16,
+    pub observations: [Observation; OBSERVATION_CAPACITY],
+    pub dynamic_fee_enabled: bool,
+    pub min_fee_rate: u32,
+    pub max_fee_rate: u32,
+    pub volatility_accumulator: u64,
+    pub volatility_updated_at: u32,
 }
 
 impl Pool {
@@ -22,7 +56,219 @@ impl Pool {
         32 + // token_vault_1
         16 + // global_liquidity
         16 + // sqrt_price_x96
+        16 + // fee_growth_global_0_x64
+        16 + // fee_growth_global_1_x64
         4 +  // current_tick
         4 +  // tick_spacing
-        1;   // bump
-}
\ No newline at end of file
+        1 +  // mint_decimals_0
+        1 +  // mint_decimals_1
+        1 +  // bump
+        1 +  // status
+        1 +  // pause_flags
+        2 +  // observation_index
+        2 +  // observation_cardinality
+        OBSERVATION_CAPACITY * Observation::SPACE + // observations
+        1 +  // dynamic_fee_enabled
+        4 +  // min_fee_rate
+        4 +  // max_fee_rate
+        8 +  // volatility_accumulator
+        4;   // volatility_updated_at
+
+    pub fn require_not_paused(&self, operation: u8) -> Result<()> {
+        require!(self.pause_flags & operation == 0, ErrorCode::OperationPaused);
+        Ok(())
+    }
+
+    /// Marks the pool as mid-operation. Callers must persist the pool with
+    /// `exit` before any CPI so a reentrant call observes the lock.
+    pub fn lock(&mut self) -> Result<()> {
+        require!(self.status == POOL_STATUS_UNLOCKED, ErrorCode::PoolLocked);
+        self.status = POOL_STATUS_LOCKED;
+        Ok(())
+    }
+
+    pub fn unlock(&mut self) {
+        self.status = POOL_STATUS_UNLOCKED;
+    }
+
+    pub fn update_sqrt_price(&mut self, sqrt_price_x96: u128, block_timestamp: u32) -> Result<()> {
+        self.write_observation(block_timestamp)?;
+        let tick_before = self.current_tick;
+        self.sqrt_price_x96 = sqrt_price_x96;
+        self.current_tick = get_tick_at_sqrt_price(sqrt_price_x96)?;
+        self.accumulate_volatility(tick_before, block_timestamp);
+        Ok(())
+    }
+
+    /// Volatility accumulator after linear decay up to `block_timestamp`.
+    pub fn decayed_volatility(&self, block_timestamp: u32) -> u64 {
+        let elapsed = block_timestamp.saturating_sub(self.volatility_updated_at);
+        if elapsed >= VOLATILITY_DECAY_PERIOD {
+            return 0;
+        }
+        ((self.volatility_accumulator as u128) * ((VOLATILITY_DECAY_PERIOD - elapsed) as u128)
+            / VOLATILITY_DECAY_PERIOD as u128) as u64
+    }
+
+    /// Fee rate charged by a swap at `block_timestamp`. Pools without dynamic
+    /// fees always charge `DEFAULT_FEE_RATE`.
+    pub fn fee_rate(&self, block_timestamp: u32) -> u32 {
+        if !self.dynamic_fee_enabled {
+            return DEFAULT_FEE_RATE;
+        }
+        let variable_fee_rate = self
+            .decayed_volatility(block_timestamp)
+            .saturating_mul(VOLATILITY_FEE_RATE_PER_TICK);
+        (self.min_fee_rate as u64)
+            .saturating_add(variable_fee_rate)
+            .min(self.max_fee_rate as u64) as u32
+    }
+
+    /// Adds the tick movement since `tick_before` to the decayed accumulator.
+    /// Tracked even while dynamic fees are off so enabling them starts warm.
+    fn accumulate_volatility(&mut self, tick_before: i32, block_timestamp: u32) {
+        let movement = self.current_tick.abs_diff(tick_before) as u64;
+        self.volatility_accumulator = self
+            .decayed_volatility(block_timestamp)
+            .saturating_add(movement);
+        self.volatility_updated_at = block_timestamp;
+    }
+
+    /// Distributes fees pro rata to `global_liquidity`, the liquidity in
+    /// range at the current price.
+    pub fn credit_fees(&mut self, fee_0: u64, fee_1: u64) -> Result<()> {
+        require!(self.global_liquidity > 0, ErrorCode::InsufficientPoolLiquidity);
+        self.fee_growth_global_0_x64 = self
+            .fee_growth_global_0_x64
+            .wrapping_add(((fee_0 as u128) << 64) / self.global_liquidity);
+        self.fee_growth_global_1_x64 = self
+            .fee_growth_global_1_x64
+            .wrapping_add(((fee_1 as u128) << 64) / self.global_liquidity);
+        Ok(())
+    }
+
+    /// Credits a swap's `fee_amount`, taken in the input token, to LPs.
+    pub fn credit_swap_fee(&mut self, fee_amount: u64, swap_token_0_for_1: bool) -> Result<()> {
+        if swap_token_0_for_1 {
+            self.credit_fees(fee_amount, 0)
+        } else {
+            self.credit_fees(0, fee_amount)
+        }
+    }
+
+    /// Adds a position's `liquidity_delta` to `global_liquidity` when the
+    /// current price is inside `[tick_lower, tick_upper)`. Out-of-range
+    /// liquidity joins once a swap crosses into the range.
+    pub fn apply_liquidity_delta(
+        &mut self,
+        tick_lower: i32,
+        tick_upper: i32,
+        liquidity_delta: i128,
+    ) -> Result<()> {
+        if (tick_lower..tick_upper).contains(&self.current_tick) {
+            self.global_liquidity = self
+                .global_liquidity
+                .checked_add_signed(liquidity_delta)
+                .ok_or(ErrorCode::InsufficientPoolLiquidity)?;
+        }
+        Ok(())
+    }
+
+    pub fn initialize_observations(&mut self, block_timestamp: u32) {
+        self.observations = [Observation::default(); OBSERVATION_CAPACITY];
+        self.observations[0] = Observation {
+            block_timestamp,
+            initialized: true,
+            ..Observation::default()
+        };
+        self.observation_index = 0;
+        self.observation_cardinality = 1;
+    }
+
+    /// Records the tick and liquidity the pool held since the last observation.
+    /// Must be called before `current_tick` or `global_liquidity` change.
+    pub fn write_observation(&mut self, block_timestamp: u32) -> Result<()> {
+        let last = self.observations[self.observation_index as usize];
+        if last.block_timestamp == block_timestamp {
+            return Ok(());
+        }
+
+        let index = (self.observation_index as usize + 1) % OBSERVATION_CAPACITY;
+        self.observations[index] =
+            last.transform(block_timestamp, self.current_tick, self.global_liquidity);
+        self.observation_index = index as u16;
+        if (self.observation_cardinality as usize) < OBSERVATION_CAPACITY {
+            self.observation_cardinality = self
+                .observation_cardinality
+                .checked_add(1)
+                .ok_or(ErrorCode::ArithmeticOverflow)?;
+        }
+        Ok(())
+    }
+
+    /// Returns `(tick_cumulative, seconds_per_liquidity_cumulative_x64)` as of
+    /// `seconds_ago` seconds before `block_timestamp`, interpolating between
+    /// stored observations or extrapolating from the latest one.
+    pub fn observe_single(&self, block_timestamp: u32, seconds_ago: u32) -> Result<(i64, u128)> {
+        let target = block_timestamp
+            .checked_sub(seconds_ago)
+            .ok_or(ErrorCode::ObservationTooOld)?;
+
+        let last = self.observations[self.observation_index as usize];
+        if target >= last.block_timestamp {
+            let observation = last.transform(target, self.current_tick, self.global_liquidity);
+            return Ok((
+                observation.tick_cumulative,
+                observation.seconds_per_liquidity_cumulative_x64,
+            ));
+        }
+
+        let cardinality = self.observation_cardinality as usize;
+        let oldest_index = if cardinality < OBSERVATION_CAPACITY {
+            0
+        } else {
+            (self.observation_index as usize + 1) % OBSERVATION_CAPACITY
+        };
+        require!(
+            self.observations[oldest_index].block_timestamp <= target,
+            ErrorCode::ObservationTooOld
+        );
+
+        for i in 0..cardinality - 1 {
+            let before = self.observations[(oldest_index + i) % OBSERVATION_CAPACITY];
+            let after = self.observations[(oldest_index + i + 1) % OBSERVATION_CAPACITY];
+            if target == before.block_timestamp {
+                return Ok((
+                    before.tick_cumulative,
+                    before.seconds_per_liquidity_cumulative_x64,
+                ));
+            }
+            if target < after.block_timestamp {
+                let delta = (after.block_timestamp - before.block_timestamp) as i64;
+                let elapsed = (target - before.block_timestamp) as i64;
+
+                let tick_cumulative = before.tick_cumulative.wrapping_add(
+                    after
+                        .tick_cumulative
+                        .wrapping_sub(before.tick_cumulative)
+                        .checked_div(delta)
+                        .ok_or(ErrorCode::ArithmeticOverflow)?
+                        .wrapping_mul(elapsed),
+                );
+                let seconds_per_liquidity_cumulative_x64 = before
+                    .seconds_per_liquidity_cumulative_x64
+                    .wrapping_add(
+                        after
+                            .seconds_per_liquidity_cumulative_x64
+                            .wrapping_sub(before.seconds_per_liquidity_cumulative_x64)
+                            .checked_mul(elapsed as u128)
+                            .ok_or(ErrorCode::ArithmeticOverflow)?
+                            / delta as u128,
+                    );
+                return Ok((tick_cumulative, seconds_per_liquidity_cumulative_x64));
+            }
+        }
+
+        Err(ErrorCode::ObservationTooOld.into())
+    }
+}
@@ -1,4 +1,7 @@
 use anchor_lang::prelude::*;
+use crate::utils::ErrorCode;
+use crate::utils::math::get_fees_owed;
+
 #[account]
 pub struct Position {
     pub liquidity: u128,
@@ -7,6 +10,13 @@ pub struct Position {
     pub owner: Pubkey,
     pub pool: Pubkey,
     pub bump: u8,
+    pub lock_until: i64,
+    pub operator: Pubkey,
+    pub fee_growth_inside_0_last_x64: u128,
+    pub fee_growth_inside_1_last_x64: u128,
+    pub tokens_owed_0: u64,
+    pub tokens_owed_1: u64,
+    pub keeper: Pubkey,
 }
 
 impl Position {
@@ -16,5 +26,92 @@ impl Position {
         4 +  // tick_upper
         32 + // owner
         32 + // pool
-        1;   // bump
-}
\ No newline at end of file
+        1 +  // bump
+        8 +  // lock_until
+        32 + // operator
+        16 + // fee_growth_inside_0_last_x64
+        16 + // fee_growth_inside_1_last_x64
+        8 +  // tokens_owed_0
+        8 +  // tokens_owed_1
+        32;  // keeper
+
+    /// Liquidity cannot be withdrawn while the position is time-locked.
+    pub fn require_unlocked(&self, unix_timestamp: i64) -> Result<()> {
+        require!(unix_timestamp >= self.lock_until, ErrorCode::PositionLocked);
+        Ok(())
+    }
+
+    /// Locked liquidity may only move into a position locked at least as long.
+    pub fn require_can_absorb(&self, source: &Position, unix_timestamp: i64) -> Result<()> {
+        require!(
+            unix_timestamp >= source.lock_until || self.lock_until >= source.lock_until,
+            ErrorCode::PositionLocked
+        );
+        Ok(())
+    }
+
+    /// The owner, or the operator it delegated to, may manage liquidity.
+    pub fn is_authorized(&self, signer: &Pubkey) -> bool {
+        *signer == self.owner || (self.operator != Pubkey::default() && *signer == self.operator)
+    }
+
+    /// The owner, the operator, or the keeper it delegated to, may compound
+    /// the position's fees.
+    pub fn can_compound(&self, signer: &Pubkey) -> bool {
+        self.is_authorized(signer) || (self.keeper != Pubkey::default() && *signer == self.keeper)
+    }
+
+    /// Moves `source`'s accrued fees into this position.
+    pub fn absorb_fees(&mut self, source: &mut Position) -> Result<()> {
+        let (owed_0, owed_1) = source.take_fees();
+        self.tokens_owed_0 = self
+            .tokens_owed_0
+            .checked_add(owed_0)
+            .ok_or(ErrorCode::ArithmeticOverflow)?;
+        self.tokens_owed_1 = self
+            .tokens_owed_1
+            .checked_add(owed_1)
+            .ok_or(ErrorCode::ArithmeticOverflow)?;
+        Ok(())
+    }
+}
+
+impl AccruesFees for Position {
+    fn fee_fields(&mut self) -> (u128, [&mut u128; 2], [&mut u64; 2]) {
+        (
+            self.liquidity,
+            [&mut self.fee_growth_inside_0_last_x64, &mut self.fee_growth_inside_1_last_x64],
+            [&mut self.tokens_owed_0, &mut self.tokens_owed_1],
+        )
+    }
+}
+
+/// Fee bookkeeping shared by `Position` and the slots of a `PositionBundle`.
+pub trait AccruesFees {
+    /// The liquidity, the fee growth inside the range at the last checkpoint
+    /// and the fees owed, for token 0 and token 1.
+    fn fee_fields(&mut self) -> (u128, [&mut u128; 2], [&mut u64; 2]);
+
+    /// Credits fees earned inside the range since the last checkpoint to
+    /// `tokens_owed_*`, given the range's current fee growth inside.
+    /// Must run before every change to `liquidity`.
+    fn accrue_fees(&mut self, fee_growth_inside_0_x64: u128, fee_growth_inside_1_x64: u128) -> Result<()> {
+        let (liquidity, last, owed) = self.fee_fields();
+        for ((fee_growth_inside_x64, last), owed) in [fee_growth_inside_0_x64, fee_growth_inside_1_x64]
+            .into_iter()
+            .zip(last)
+            .zip(owed)
+        {
+            let earned = get_fees_owed(liquidity, fee_growth_inside_x64.wrapping_sub(*last))?;
+            *owed = owed.checked_add(earned).ok_or(ErrorCode::ArithmeticOverflow)?;
+            *last = fee_growth_inside_x64;
+        }
+        Ok(())
+    }
+
+    /// Clears and returns the accrued fees.
+    fn take_fees(&mut self) -> (u64, u64) {
+        let (_, _, [owed_0, owed_1]) = self.fee_fields();
+        (std::mem::take(owed_0), std::mem::take(owed_1))
+    }
+}
@@ -0,0 +1,98 @@
+use anchor_lang::prelude::*;
+use crate::utils::ErrorCode;
+use crate::states::AccruesFees;
+
+pub const POSITION_BUNDLE_SIZE: usize = 64;
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default, Debug)]
+pub struct BundledPosition {
+    pub liquidity: u128,
+    pub tick_lower: i32,
+    pub tick_upper: i32,
+    pub fee_growth_inside_0_last_x64: u128,
+    pub fee_growth_inside_1_last_x64: u128,
+    pub tokens_owed_0: u64,
+    pub tokens_owed_1: u64,
+}
+
+impl BundledPosition {
+    pub const SPACE: usize = 16 + // liquidity
+        4 +  // tick_lower
+        4 +  // tick_upper
+        16 + // fee_growth_inside_0_last_x64
+        16 + // fee_growth_inside_1_last_x64
+        8 +  // tokens_owed_0
+        8;   // tokens_owed_1
+}
+
+impl AccruesFees for BundledPosition {
+    fn fee_fields(&mut self) -> (u128, [&mut u128; 2], [&mut u64; 2]) {
+        (
+            self.liquidity,
+            [&mut self.fee_growth_inside_0_last_x64, &mut self.fee_growth_inside_1_last_x64],
+            [&mut self.tokens_owed_0, &mut self.tokens_owed_1],
+        )
+    }
+}
+
+/// Up to `POSITION_BUNDLE_SIZE` independently ranged positions of one owner
+/// in one pool, sharing a single account. Bit `i` of `bitmap` marks slot `i`
+/// as open.
+#[account]
+pub struct PositionBundle {
+    pub owner: Pubkey,
+    pub pool: Pubkey,
+    pub bitmap: u64,
+    pub positions: [BundledPosition; POSITION_BUNDLE_SIZE],
+    pub bump: u8,
+}
+
+impl PositionBundle {
+    pub const SPACE: usize = 8 + // discriminator
+        32 + // owner
+        32 + // pool
+        8 +  // bitmap
+        POSITION_BUNDLE_SIZE * BundledPosition::SPACE + // positions
+        1;   // bump
+
+    pub fn is_slot_open(&self, slot: u8) -> bool {
+        (slot as usize) < POSITION_BUNDLE_SIZE && self.bitmap & (1u64 << slot) != 0
+    }
+
+    pub fn open_slot(&mut self, slot: u8, tick_lower: i32, tick_upper: i32) -> Result<()> {
+        require!(
+            (slot as usize) < POSITION_BUNDLE_SIZE && !self.is_slot_open(slot),
+            ErrorCode::InvalidBundleSlot
+        );
+        self.bitmap |= 1u64 << slot;
+        self.positions[slot as usize] = BundledPosition {
+            tick_lower,
+            tick_upper,
+            ..Default::default()
+        };
+        Ok(())
+    }
+
+    pub fn close_slot(&mut self, slot: u8) -> Result<()> {
+        require!(self.is_slot_open(slot), ErrorCode::InvalidBundleSlot);
+        self.bitmap &= !(1u64 << slot);
+        self.positions[slot as usize] = BundledPosition::default();
+        Ok(())
+    }
+
+    /// Returns the open slot, checking it covers `tick_lower..tick_upper`.
+    pub fn get_slot_mut(
+        &mut self,
+        slot: u8,
+        tick_lower: i32,
+        tick_upper: i32,
+    ) -> Result<&mut BundledPosition> {
+        require!(self.is_slot_open(slot), ErrorCode::InvalidBundleSlot);
+        let position = &mut self.positions[slot as usize];
+        require!(
+            position.tick_lower == tick_lower && position.tick_upper == tick_upper,
+            ErrorCode::InvalidPositionRange
+        );
+        Ok(position)
+    }
+}
@@ -1,11 +1,26 @@
 use anchor_lang::prelude::*;
+use crate::states::Pool;
 use crate::utils::ErrorCode;
+use crate::utils::init_pda_if_needed;
+use crate::utils::math::{get_amount_out_at_sqrt_price, get_sqrt_price_from_tick};
 
 #[account]
+#[derive(Default)]
 pub struct TickInfo {
     pub initialized: bool,
     pub liquidity_gross: u128,
     pub liquidity_net: i128,
+    /// Fee growth on the other side of this tick from the current price.
+    pub fee_growth_outside_0_x64: u128,
+    pub fee_growth_outside_1_x64: u128,
+    /// Amount resting in limit orders on this tick, in the token they sell.
+    /// It is not active liquidity and earns no swap fees.
+    pub limit_order_amount: u64,
+    /// Whether the resting orders sell token 0, filling when the price
+    /// crosses the tick upwards, rather than token 1.
+    pub limit_order_zero_for_one: bool,
+    /// Bumped every time the resting orders fill.
+    pub limit_order_epoch: u64,
 }
 
 impl TickInfo {
@@ -14,16 +29,26 @@ impl TickInfo {
         8 + // discriminator
         16 + // liquidity_gross
         16 + // liquidity_net
-        1;   // initialized
+        1 +  // initialized
+        16 + // fee_growth_outside_0_x64
+        16 + // fee_growth_outside_1_x64
+        8 +  // limit_order_amount
+        1 +  // limit_order_zero_for_one
+        8;   // limit_order_epoch
 
     pub fn update_liquidity(&mut self, liquidity_delta: i128, is_lower: bool) -> Result<()> {
         if !self.initialized {
             self.initialized = true;
         }
-        self.liquidity_gross = self
-            .liquidity_gross
-            .checked_add(liquidity_delta.unsigned_abs())
-            .ok_or(ErrorCode::ArithmeticOverflow)?;
+        self.liquidity_gross = if liquidity_delta >= 0 {
+            self.liquidity_gross
+                .checked_add(liquidity_delta.unsigned_abs())
+                .ok_or(ErrorCode::ArithmeticOverflow)?
+        } else {
+            self.liquidity_gross
+                .checked_sub(liquidity_delta.unsigned_abs())
+                .ok_or(ErrorCode::InsufficientLiquidity)?
+        };
         if is_lower {
             self.liquidity_net = self
                 .liquidity_net
@@ -37,6 +62,79 @@ impl TickInfo {
         }
         Ok(())
     }
+
+    /// Flips the fee growth outside as the price crosses this tick and
+    /// returns the liquidity that becomes active when crossing upwards.
+    pub fn cross(&mut self, fee_growth_global_0_x64: u128, fee_growth_global_1_x64: u128) -> i128 {
+        self.fee_growth_outside_0_x64 =
+            fee_growth_global_0_x64.wrapping_sub(self.fee_growth_outside_0_x64);
+        self.fee_growth_outside_1_x64 =
+            fee_growth_global_1_x64.wrapping_sub(self.fee_growth_outside_1_x64);
+        self.liquidity_net
+    }
+
+    /// Fills the resting limit orders if crossing `tick` in this direction
+    /// sells their token and `budget` of the swapper's input pays for all of
+    /// them at the tick's price. Returns the amount the orders sold and the
+    /// input they are owed, which their owners claim later.
+    pub fn fill_limit_orders(&mut self, tick: i32, upwards: bool, budget: u64) -> Result<Option<(u64, u64)>> {
+        if self.limit_order_amount == 0 || self.limit_order_zero_for_one != upwards {
+            return Ok(None);
+        }
+        // Rounded down once for the whole tick, so the sum of the claims,
+        // each rounded down on its own, never exceeds it.
+        let proceeds = get_amount_out_at_sqrt_price(
+            self.limit_order_amount,
+            get_sqrt_price_from_tick(tick)?,
+            upwards,
+        )?;
+        if proceeds > budget {
+            return Ok(None);
+        }
+        let filled = self.limit_order_amount;
+        self.limit_order_amount = 0;
+        self.limit_order_epoch = self
+            .limit_order_epoch
+            .checked_add(1)
+            .ok_or(ErrorCode::ArithmeticOverflow)?;
+        Ok(Some((filled, proceeds)))
+    }
+}
+
+/// Fee growth per unit of liquidity earned inside `[tick_lower, tick_upper)`,
+/// from the fee growth outside each boundary tick.
+pub fn get_fee_growth_inside(
+    pool: &Pool,
+    tick_lower: i32,
+    lower: &TickInfo,
+    tick_upper: i32,
+    upper: &TickInfo,
+) -> (u128, u128) {
+    let inside = |global: u128, lower_outside: u128, upper_outside: u128| {
+        let below = if pool.current_tick >= tick_lower {
+            lower_outside
+        } else {
+            global.wrapping_sub(lower_outside)
+        };
+        let above = if pool.current_tick < tick_upper {
+            upper_outside
+        } else {
+            global.wrapping_sub(upper_outside)
+        };
+        global.wrapping_sub(below).wrapping_sub(above)
+    };
+    (
+        inside(
+            pool.fee_growth_global_0_x64,
+            lower.fee_growth_outside_0_x64,
+            upper.fee_growth_outside_0_x64,
+        ),
+        inside(
+            pool.fee_growth_global_1_x64,
+            lower.fee_growth_outside_1_x64,
+            upper.fee_growth_outside_1_x64,
+        ),
+    )
 }
 
 pub const TICKS_PER_ARRAY: usize = 30;
@@ -54,35 +152,167 @@ impl TickArray {
     pub const SPACE: usize = 8 + // discriminator
         32 + // pool
         4 +  // starting_tick
-        TICKS_PER_ARRAY * 48 + // ticks
+        TICKS_PER_ARRAY * (TickInfo::SPACE - 8) + // ticks, stored without discriminators
         1;   // bump
 
+    /// Start of the array holding `tick`. Rounds towards negative infinity so
+    /// ticks below zero land in their own arrays.
     pub fn get_starting_tick_index(tick: i32, tick_spacing: i32) -> i32 {
-        let ticks_per_array_i32 = TICKS_PER_ARRAY as i32;
-        let array_idx = tick
-            .checked_div(tick_spacing)
-            .expect("Div by zero: tick_spacing")
-            .checked_div(ticks_per_array_i32)
-            .expect("Div by zero: TICKS_PER_ARRAY");
-        array_idx
-            .checked_mul(ticks_per_array_i32)
-            .expect("Mul overflow")
-            .checked_mul(tick_spacing)
+        let ticks_in_array = tick_spacing
+            .checked_mul(TICKS_PER_ARRAY as i32)
+            .expect("Mul overflow");
+        tick.div_euclid(ticks_in_array)
+            .checked_mul(ticks_in_array)
             .expect("Mul overflow")
     }
+
+    fn get_tick_offset(&self, tick: i32, tick_spacing: i32) -> Result<usize> {
+        let offset = tick
+            .checked_sub(self.starting_tick)
+            .ok_or(ErrorCode::ArithmeticOverflow)?
+            .div_euclid(tick_spacing);
+        require!(
+            (0..TICKS_PER_ARRAY as i32).contains(&offset),
+            ErrorCode::InvalidTickArrayStartIndex
+        );
+        Ok(offset as usize)
+    }
+
+    pub fn get_tick_info(&self, tick: i32, tick_spacing: i32) -> Result<&TickInfo> {
+        Ok(&self.ticks[self.get_tick_offset(tick, tick_spacing)?])
+    }
+
     pub fn get_tick_info_mutable(&mut self, tick: i32, tick_spacing: i32) -> Result<&mut TickInfo> {
-        let ticks_per_array_i32 = TICKS_PER_ARRAY as i32;
-        let offset = (tick
-            .checked_div(tick_spacing)
-            .ok_or(ErrorCode::ArithmeticOverflow)?)
-        .checked_sub(
-            self.starting_tick
-                .checked_div(tick_spacing)
-                .ok_or(ErrorCode::ArithmeticOverflow)?,
-        )
-        .ok_or(ErrorCode::ArithmeticOverflow)?
-        .checked_rem(ticks_per_array_i32)
-        .ok_or(ErrorCode::ArithmeticOverflow)? as usize;
+        let offset = self.get_tick_offset(tick, tick_spacing)?;
         Ok(&mut self.ticks[offset])
     }
+
+    /// Returns `tick`, initializing it on first use. A new tick counts all
+    /// fee growth so far as below it when the price is at or above it, as if
+    /// it had always been crossed.
+    pub fn get_tick_info_initialized(&mut self, pool: &Pool, tick: i32) -> Result<&mut TickInfo> {
+        let tick_info = self.get_tick_info_mutable(tick, pool.tick_spacing)?;
+        if !tick_info.initialized {
+            if tick <= pool.current_tick {
+                tick_info.fee_growth_outside_0_x64 = pool.fee_growth_global_0_x64;
+                tick_info.fee_growth_outside_1_x64 = pool.fee_growth_global_1_x64;
+            }
+            tick_info.initialized = true;
+        }
+        Ok(tick_info)
+    }
+
+    /// Applies `liquidity_delta` to `tick` and returns the updated tick.
+    pub fn update_tick(
+        &mut self,
+        pool: &Pool,
+        tick: i32,
+        liquidity_delta: i128,
+        is_lower: bool,
+    ) -> Result<TickInfo> {
+        let tick_info = self.get_tick_info_initialized(pool, tick)?;
+        tick_info.update_liquidity(liquidity_delta, is_lower)?;
+        Ok(tick_info.clone())
+    }
+
+    /// Fee growth inside a range whose boundary ticks live in the given
+    /// arrays, which may be the same one.
+    pub fn get_fee_growth_inside(
+        lower_tick_array: &TickArray,
+        upper_tick_array: &TickArray,
+        pool: &Pool,
+        tick_lower: i32,
+        tick_upper: i32,
+    ) -> Result<(u128, u128)> {
+        Ok(get_fee_growth_inside(
+            pool,
+            tick_lower,
+            lower_tick_array.get_tick_info(tick_lower, pool.tick_spacing)?,
+            tick_upper,
+            upper_tick_array.get_tick_info(tick_upper, pool.tick_spacing)?,
+        ))
+    }
+
+    /// Reads a tick array straight from `account`, for arrays that are not
+    /// held as an `Account` for the whole instruction.
+    pub fn load(account: &AccountInfo) -> Result<TickArray> {
+        require_keys_eq!(*account.owner, crate::ID, ErrorCode::InvalidTickArrayAccount);
+        TickArray::try_deserialize(&mut &account.try_borrow_data()?[..])
+    }
+
+    /// Writes the array back to `account`.
+    pub fn store(&self, account: &AccountInfo) -> Result<()> {
+        self.try_serialize(&mut &mut account.try_borrow_mut_data()?[..])
+    }
+
+    /// Creates the array of `pool` holding `tick` at `account` unless it
+    /// already exists.
+    pub fn init_if_needed<'info>(
+        payer: &Signer<'info>,
+        system_program: &Program<'info, System>,
+        account: &AccountInfo<'info>,
+        pool: Pubkey,
+        tick: i32,
+        tick_spacing: i32,
+    ) -> Result<()> {
+        let starting_tick = TickArray::get_starting_tick_index(tick, tick_spacing);
+        init_pda_if_needed(
+            payer,
+            system_program,
+            account,
+            &[b"tick_array", pool.as_ref(), &starting_tick.to_le_bytes()],
+            TickArray::SPACE,
+            |bump| TickArray {
+                pool,
+                starting_tick,
+                ticks: core::array::from_fn(|_| TickInfo::default()),
+                bump,
+            },
+        )
+    }
+
+    /// Applies a position's `liquidity_delta` to its lower and upper ticks
+    /// and returns the fee growth inside its range afterwards.
+    /// When both ticks live in one array Anchor hands out two copies of the
+    /// same account, so both updates go to the lower copy and are mirrored
+    /// into the upper one; whichever copy is written back last carries both.
+    pub fn update_position_ticks(
+        lower_tick_array: &mut Account<TickArray>,
+        upper_tick_array: &mut Account<TickArray>,
+        pool: &Pool,
+        lower_tick: i32,
+        upper_tick: i32,
+        liquidity_delta: i128,
+    ) -> Result<(u128, u128)> {
+        let lower = lower_tick_array.update_tick(pool, lower_tick, liquidity_delta, true)?;
+        let upper = if lower_tick_array.key() == upper_tick_array.key() {
+            let upper = lower_tick_array.update_tick(pool, upper_tick, liquidity_delta, false)?;
+            upper_tick_array.set_inner((**lower_tick_array).clone());
+            upper
+        } else {
+            upper_tick_array.update_tick(pool, upper_tick, liquidity_delta, false)?
+        };
+        Ok(get_fee_growth_inside(pool, lower_tick, &lower, upper_tick, &upper))
+    }
+
+    /// Same as `update_position_ticks` for arrays passed as raw accounts: each
+    /// one is read and written back in turn, so the two may be the same.
+    pub fn update_position_ticks_in_place<'info>(
+        lower_tick_array: &AccountInfo<'info>,
+        upper_tick_array: &AccountInfo<'info>,
+        pool: &Pool,
+        lower_tick: i32,
+        upper_tick: i32,
+        liquidity_delta: i128,
+    ) -> Result<(u128, u128)> {
+        let update = |account: &AccountInfo<'info>, tick: i32, is_lower: bool| {
+            let mut tick_array = TickArray::load(account)?;
+            let tick_info = tick_array.update_tick(pool, tick, liquidity_delta, is_lower)?;
+            tick_array.store(account)?;
+            Ok::<TickInfo, Error>(tick_info)
+        };
+        let lower = update(lower_tick_array, lower_tick, true)?;
+        let upper = update(upper_tick_array, upper_tick, false)?;
+        Ok(get_fee_growth_inside(pool, lower_tick, &lower, upper_tick, &upper))
+    }
 }
@@ -48,4 +48,66 @@ pub enum ErrorCode {
     InvalidTickArrayBump,
     #[msg("Invalid tick array pool")]
     InvalidTickArrayPool,
-}
+    #[msg("Requested observation is older than the oldest stored observation")]
+    ObservationTooOld,
+    #[msg("TWAP window must be greater than zero")]
+    InvalidTwapWindow,
+    #[msg("Token account is not the pool vault")]
+    InvalidPoolVault,
+    #[msg("Token account mint does not match the pool mint")]
+    InvalidTokenMint,
+    #[msg("Token account is not owned by the signer")]
+    InvalidTokenAccountOwner,
+    #[msg("Pool is locked by an in-progress operation")]
+    PoolLocked,
+    #[msg("Operation is paused for this pool")]
+    OperationPaused,
+    #[msg("Unknown pause flags")]
+    InvalidPauseFlags,
+    #[msg("Mint has an unsupported Token-2022 extension")]
+    UnsupportedMintExtension,
+    #[msg("Transfer hook program is not trusted")]
+    UntrustedTransferHookProgram,
+    #[msg("Invalid config authority")]
+    InvalidConfigAuthority,
+    #[msg("Too many trusted transfer hook programs")]
+    TooManyTransferHookPrograms,
+    #[msg("Pool has no native SOL side")]
+    NativeMintNotInPool,
+    #[msg("Token mints must be ordered with token_mint_0 < token_mint_1")]
+    InvalidMintOrder,
+    #[msg("Mint has a freeze authority and is not allowed by config")]
+    MintHasFreezeAuthority,
+    #[msg("Too many allowed freezable mints")]
+    TooManyFreezableMints,
+    #[msg("Flash loan was not repaid with fee")]
+    FlashLoanNotRepaid,
+    #[msg("Invalid flash callback program")]
+    InvalidFlashCallbackProgram,
+    #[msg("Swap route is empty, too long, repeats a pool or does not chain")]
+    InvalidRoute,
+    #[msg("Fee rates must satisfy min <= max <= MAX_FEE_RATE")]
+    InvalidFeeRate,
+    #[msg("Limit order must sell the same token as the orders already on this tick")]
+    InvalidLimitOrderDirection,
+    #[msg("Limit order has not been filled")]
+    LimitOrderNotFilled,
+    #[msg("Position is locked")]
+    PositionLocked,
+    #[msg("Lock can only be extended into the future")]
+    InvalidLockUntil,
+    #[msg("Position bundle slot is out of range or in the wrong state")]
+    InvalidBundleSlot,
+    #[msg("Position bundle still has open positions")]
+    PositionBundleNotEmpty,
+    #[msg("Batch is empty, too long or its remaining accounts do not match")]
+    InvalidBatch,
+    #[msg("Position has no fees to compound")]
+    NoFeesToCompound,
+    #[msg("Account is not the expected program address")]
+    InvalidPdaAccount,
+    #[msg("A tick array the price moves through was not passed")]
+    MissingTickArray,
+    #[msg("Limit order has been filled and must be claimed")]
+    LimitOrderFilled,
+}
\ No newline at end of file
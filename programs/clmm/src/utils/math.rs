@@ -1,6 +1,19 @@
 use crate::utils::ErrorCode;
 use anchor_lang::prelude::*;
 
+pub const MIN_TICK: i32 = -887272;
+pub const MAX_TICK: i32 = -MIN_TICK;
+
+pub const MIN_SQRT_PRICE_X96: u128 = (1u128 << 96) - (MAX_TICK as u128) * 1_000_000;
+pub const MAX_SQRT_PRICE_X96: u128 = (1u128 << 96) + (MAX_TICK as u128) * 1_000_000;
+
+pub const FLASH_FEE_BPS: u64 = 10;
+
+/// Swap fee rates are expressed in hundredths of a basis point.
+pub const FEE_RATE_DENOMINATOR: u32 = 1_000_000;
+pub const DEFAULT_FEE_RATE: u32 = 1_000; // 0.1%
+pub const MAX_FEE_RATE: u32 = 100_000; // 10%
+
 pub fn get_sqrt_price_from_tick(tick: i32) -> Result<u128> {
     // This is a simplification; real math is logarithmic.
     let base_sqrt_price = 1u128 << 96;
@@ -15,9 +28,10 @@ pub fn get_tick_at_sqrt_price(sqrt_price_x96: u128) -> Result<i32> {
     let base_sqrt_price = 1u128 << 96;
     let adjustment_factor = 1_000_000_000 / 1000;
 
+    // Rounds down so a price is never reported above the tick it sits in.
     let diff = sqrt_price_x96 as i128 - base_sqrt_price as i128;
     let tick = diff
-        .checked_div(adjustment_factor as i128)
+        .checked_div_euclid(adjustment_factor as i128)
         .ok_or(ErrorCode::ArithmeticOverflow)? as i32;
     Ok(tick)
 }
@@ -44,21 +58,67 @@ pub fn get_amounts_for_liquidity(
     Ok((amount0, amount1))
 }
 
+/// Largest liquidity `get_amounts_for_liquidity` can back with the given amounts.
+pub fn get_liquidity_for_amounts(
+    current_sqrt_price_x96: u128,
+    lower_sqrt_price_x96: u128,
+    upper_sqrt_price_x96: u128,
+    amount_0: u64,
+    amount_1: u64,
+) -> Result<u128> {
+    let liquidity = if current_sqrt_price_x96 >= lower_sqrt_price_x96 && current_sqrt_price_x96 < upper_sqrt_price_x96 {
+        (amount_0.min(amount_1) as u128) * 2
+    } else if current_sqrt_price_x96 < lower_sqrt_price_x96 {
+        amount_0 as u128
+    } else {
+        amount_1 as u128
+    };
+    Ok(liquidity)
+}
+
+/// Portion of `amount_in` to swap so that the remainder and the swap output
+/// match the ratio `get_amounts_for_liquidity` needs for the range at the
+/// current price.
+pub fn get_zap_swap_amount(
+    current_sqrt_price_x96: u128,
+    lower_sqrt_price_x96: u128,
+    upper_sqrt_price_x96: u128,
+    amount_in: u64,
+    input_is_token_0: bool,
+    fee_rate: u32,
+) -> Result<u64> {
+    let swap_amount = if current_sqrt_price_x96 >= lower_sqrt_price_x96 && current_sqrt_price_x96 < upper_sqrt_price_x96 {
+        // Equal amounts are needed: amount_in - x == x - fee(x).
+        let amount = (amount_in as u128)
+            .checked_mul(FEE_RATE_DENOMINATOR as u128)
+            .ok_or(ErrorCode::ArithmeticOverflow)?
+            / (2 * FEE_RATE_DENOMINATOR as u128 - fee_rate as u128);
+        amount as u64
+    } else if (current_sqrt_price_x96 < lower_sqrt_price_x96) == input_is_token_0 {
+        0
+    } else {
+        amount_in
+    };
+    Ok(swap_amount)
+}
+
 pub fn swap_segment(
     current_sqrt_price_x96: u128,
     global_liquidity: u128,
     amount_remaining_in: u64,
     swap_token_0_for_1: bool,
-) -> Result<(u64, u64, u128)> {
+    fee_rate: u32,
+) -> Result<(u64, u64, u64, u128)> {
     if global_liquidity == 0 {
         return Err(ErrorCode::InsufficientPoolLiquidity.into());
     }
 
     let amount_in_used = amount_remaining_in;
+    let fee_amount = ((amount_in_used as u128) * (fee_rate as u128) / FEE_RATE_DENOMINATOR as u128) as u64;
     // This is a simplified calculation and does not represent a real AMM curve.
     let amount_out_calculated = amount_in_used
-        .checked_sub(amount_in_used / 1000)
-        .ok_or(ErrorCode::ArithmeticOverflow)?; // Simple 0.1% fee
+        .checked_sub(fee_amount)
+        .ok_or(ErrorCode::ArithmeticOverflow)?;
 
     let new_sqrt_price = if swap_token_0_for_1 {
         current_sqrt_price_x96
@@ -71,5 +131,51 @@ pub fn swap_segment(
             .ok_or(ErrorCode::ArithmeticOverflow)?
     };
 
-    Ok((amount_in_used, amount_out_calculated, new_sqrt_price))
-}
\ No newline at end of file
+    Ok((amount_in_used, amount_out_calculated, fee_amount, new_sqrt_price))
+}
+
+/// Converts `amount` of the input token at a fixed `sqrt_price_x96`. The price
+/// is applied in two half-steps so the intermediate products fit in a u128.
+pub fn get_amount_out_at_sqrt_price(
+    amount: u64,
+    sqrt_price_x96: u128,
+    zero_for_one: bool,
+) -> Result<u64> {
+    let sqrt_price_x48 = sqrt_price_x96 >> 48;
+    require!(sqrt_price_x48 > 0, ErrorCode::InvalidPrice);
+
+    let mut amount_out = amount as u128;
+    for _ in 0..2 {
+        amount_out = if zero_for_one {
+            amount_out
+                .checked_mul(sqrt_price_x48)
+                .ok_or(ErrorCode::ArithmeticOverflow)?
+                >> 48
+        } else {
+            (amount_out
+                .checked_shl(48)
+                .filter(|shifted| shifted >> 48 == amount_out)
+                .ok_or(ErrorCode::ArithmeticOverflow)?)
+                / sqrt_price_x48
+        };
+    }
+    u64::try_from(amount_out).map_err(|_| ErrorCode::ArithmeticOverflow.into())
+}
+
+/// Fees earned by `liquidity` over a Q64.64 fee-growth delta, rounded down.
+pub fn get_fees_owed(liquidity: u128, fee_growth_delta_x64: u128) -> Result<u64> {
+    let owed = liquidity
+        .checked_mul(fee_growth_delta_x64)
+        .ok_or(ErrorCode::ArithmeticOverflow)?
+        >> 64;
+    u64::try_from(owed).map_err(|_| ErrorCode::ArithmeticOverflow.into())
+}
+
+pub fn get_flash_fee(amount: u64) -> Result<u64> {
+    // Rounded up so small loans still pay a fee.
+    let fee = (amount as u128)
+        .checked_mul(FLASH_FEE_BPS as u128)
+        .ok_or(ErrorCode::ArithmeticOverflow)?
+        .div_ceil(10_000);
+    u64::try_from(fee).map_err(|_| ErrorCode::ArithmeticOverflow.into())
+}
@@ -2,4 +2,13 @@ pub mod errors;
 pub use errors::*;
 
 pub mod math;
-pub use math::*;
\ No newline at end of file
+pub use math::*;
+
+pub mod token;
+pub use token::*;
+
+pub mod pda;
+pub use pda::*;
+
+#[cfg(feature = "cpi")]
+pub mod twap;
\ No newline at end of file
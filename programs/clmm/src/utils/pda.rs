@@ -0,0 +1,82 @@
+use anchor_lang::prelude::*;
+use anchor_lang::system_program::{
+    allocate, assign, create_account, transfer, Allocate, Assign, CreateAccount, Transfer,
+};
+use crate::utils::ErrorCode;
+
+/// Checks that `account` is the program PDA for `seeds` and, if it does not
+/// exist yet, creates it holding `value(bump)`. An address that was already
+/// sent lamports is topped up, allocated and assigned instead, since
+/// `create_account` refuses accounts with a balance.
+pub fn init_pda_if_needed<'info, T: AccountSerialize>(
+    payer: &Signer<'info>,
+    system_program: &Program<'info, System>,
+    account: &AccountInfo<'info>,
+    seeds: &[&[u8]],
+    space: usize,
+    value: impl FnOnce(u8) -> T,
+) -> Result<()> {
+    let (address, bump) = Pubkey::find_program_address(seeds, &crate::ID);
+    require_keys_eq!(account.key(), address, ErrorCode::InvalidPdaAccount);
+    if account.owner == &crate::ID {
+        return Ok(());
+    }
+
+    let bump_seed = [bump];
+    let mut signer_seeds = seeds.to_vec();
+    signer_seeds.push(&bump_seed);
+    let signer_seeds = [signer_seeds.as_slice()];
+    let rent_exempt_lamports = Rent::get()?.minimum_balance(space);
+
+    if account.lamports() == 0 {
+        create_account(
+            CpiContext::new_with_signer(
+                system_program.to_account_info(),
+                CreateAccount {
+                    from: payer.to_account_info(),
+                    to: account.clone(),
+                },
+                &signer_seeds,
+            ),
+            rent_exempt_lamports,
+            space as u64,
+            &crate::ID,
+        )?;
+    } else {
+        let shortfall = rent_exempt_lamports.saturating_sub(account.lamports());
+        if shortfall > 0 {
+            transfer(
+                CpiContext::new(
+                    system_program.to_account_info(),
+                    Transfer {
+                        from: payer.to_account_info(),
+                        to: account.clone(),
+                    },
+                ),
+                shortfall,
+            )?;
+        }
+        allocate(
+            CpiContext::new_with_signer(
+                system_program.to_account_info(),
+                Allocate {
+                    account_to_allocate: account.clone(),
+                },
+                &signer_seeds,
+            ),
+            space as u64,
+        )?;
+        assign(
+            CpiContext::new_with_signer(
+                system_program.to_account_info(),
+                Assign {
+                    account_to_assign: account.clone(),
+                },
+                &signer_seeds,
+            ),
+            &crate::ID,
+        )?;
+    }
+    value(bump).try_serialize(&mut &mut account.try_borrow_mut_data()?[..])?;
+    Ok(())
+}
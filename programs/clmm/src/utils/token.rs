@@ -0,0 +1,187 @@
+use anchor_lang::prelude::*;
+use anchor_lang::system_program;
+use anchor_spl::token::spl_token;
+use anchor_spl::token_2022::spl_token_2022::extension::{
+    transfer_fee::TransferFeeConfig, transfer_hook, BaseStateWithExtensions, ExtensionType,
+    StateWithExtensions,
+};
+use anchor_spl::token_2022::spl_token_2022::onchain::invoke_transfer_checked;
+use anchor_spl::token_2022::spl_token_2022::state::Mint as MintState;
+use anchor_spl::token_2022::spl_token_2022;
+use anchor_spl::token_interface::{
+    self, CloseAccount, Mint, SyncNative, TokenAccount, TokenInterface,
+};
+use crate::states::{Config, Pool};
+use crate::utils::ErrorCode;
+
+/// Transfers use `invoke_transfer_checked` so that, for mints with a transfer
+/// hook, the hook's extra accounts are resolved from `remaining_accounts`
+/// and forwarded with the CPI.
+pub fn transfer_from_user_to_vault<'info>(
+    authority: &Signer<'info>,
+    from: &InterfaceAccount<'info, TokenAccount>,
+    to_vault: &InterfaceAccount<'info, TokenAccount>,
+    mint: &InterfaceAccount<'info, Mint>,
+    token_program: &Interface<'info, TokenInterface>,
+    remaining_accounts: &[AccountInfo<'info>],
+    amount: u64,
+) -> Result<()> {
+    invoke_transfer_checked(
+        token_program.key,
+        from.to_account_info(),
+        mint.to_account_info(),
+        to_vault.to_account_info(),
+        authority.to_account_info(),
+        remaining_accounts,
+        amount,
+        mint.decimals,
+        &[],
+    )
+    .map_err(Into::into)
+}
+
+pub fn transfer_from_vault_to_user<'info>(
+    pool: &Account<'info, Pool>,
+    from_vault: &InterfaceAccount<'info, TokenAccount>,
+    to: &InterfaceAccount<'info, TokenAccount>,
+    mint: &InterfaceAccount<'info, Mint>,
+    token_program: &Interface<'info, TokenInterface>,
+    remaining_accounts: &[AccountInfo<'info>],
+    amount: u64,
+) -> Result<()> {
+    let tick_spacing = pool.tick_spacing.to_le_bytes();
+    let bump = [pool.bump];
+    let signer_seeds: &[&[&[u8]]] = &[&[
+        b"pool".as_ref(),
+        pool.token_mint_0.as_ref(),
+        pool.token_mint_1.as_ref(),
+        &tick_spacing,
+        &bump,
+    ]];
+
+    invoke_transfer_checked(
+        token_program.key,
+        from_vault.to_account_info(),
+        mint.to_account_info(),
+        to.to_account_info(),
+        pool.to_account_info(),
+        remaining_accounts,
+        amount,
+        mint.decimals,
+        signer_seeds,
+    )
+    .map_err(Into::into)
+}
+
+pub fn is_native_mint(mint: &Pubkey) -> bool {
+    *mint == spl_token::native_mint::ID || *mint == spl_token_2022::native_mint::ID
+}
+
+/// Moves `lamports` from the signer into its wSOL account and syncs the
+/// token balance so the lamports can be transferred as wrapped SOL.
+pub fn wrap_native<'info>(
+    payer: &Signer<'info>,
+    token_account: &InterfaceAccount<'info, TokenAccount>,
+    token_program: &Interface<'info, TokenInterface>,
+    system_program: &Program<'info, System>,
+    lamports: u64,
+) -> Result<()> {
+    system_program::transfer(
+        CpiContext::new(
+            system_program.to_account_info(),
+            system_program::Transfer {
+                from: payer.to_account_info(),
+                to: token_account.to_account_info(),
+            },
+        ),
+        lamports,
+    )?;
+
+    token_interface::sync_native(CpiContext::new(
+        token_program.to_account_info(),
+        SyncNative {
+            account: token_account.to_account_info(),
+        },
+    ))
+}
+
+/// Closes the signer's wSOL account, returning its whole balance to the
+/// signer as lamports.
+pub fn unwrap_native<'info>(
+    owner: &Signer<'info>,
+    token_account: &InterfaceAccount<'info, TokenAccount>,
+    token_program: &Interface<'info, TokenInterface>,
+) -> Result<()> {
+    token_interface::close_account(CpiContext::new(
+        token_program.to_account_info(),
+        CloseAccount {
+            account: token_account.to_account_info(),
+            destination: owner.to_account_info(),
+            authority: owner.to_account_info(),
+        },
+    ))
+}
+
+/// Fee withheld by the transfer-fee extension when sending `pre_fee_amount`.
+pub fn get_transfer_fee(mint: &InterfaceAccount<Mint>, pre_fee_amount: u64) -> Result<u64> {
+    let mint_info = mint.to_account_info();
+    let mint_data = mint_info.try_borrow_data()?;
+    let mint_state = StateWithExtensions::<MintState>::unpack(&mint_data)?;
+
+    let fee = match mint_state.get_extension::<TransferFeeConfig>() {
+        Ok(config) => config
+            .calculate_epoch_fee(Clock::get()?.epoch, pre_fee_amount)
+            .ok_or(ErrorCode::ArithmeticOverflow)?,
+        Err(_) => 0,
+    };
+    Ok(fee)
+}
+
+/// Fee that must be added on top of `post_fee_amount` so the receiver ends up
+/// with exactly `post_fee_amount`.
+pub fn get_transfer_inverse_fee(mint: &InterfaceAccount<Mint>, post_fee_amount: u64) -> Result<u64> {
+    let mint_info = mint.to_account_info();
+    let mint_data = mint_info.try_borrow_data()?;
+    let mint_state = StateWithExtensions::<MintState>::unpack(&mint_data)?;
+
+    let fee = match mint_state.get_extension::<TransferFeeConfig>() {
+        Ok(config) => config
+            .calculate_inverse_epoch_fee(Clock::get()?.epoch, post_fee_amount)
+            .ok_or(ErrorCode::ArithmeticOverflow)?,
+        Err(_) => 0,
+    };
+    Ok(fee)
+}
+
+pub fn validate_mint_extensions(mint: &InterfaceAccount<Mint>, config: &Config) -> Result<()> {
+    let mint_info = mint.to_account_info();
+    let mint_data = mint_info.try_borrow_data()?;
+    let mint_state = StateWithExtensions::<MintState>::unpack(&mint_data)?;
+
+    require!(
+        mint_state.base.freeze_authority.is_none() || config.is_freezable_mint_allowed(&mint.key()),
+        ErrorCode::MintHasFreezeAuthority
+    );
+
+    if let Some(program_id) = transfer_hook::get_program_id(&mint_state) {
+        require!(
+            config.is_trusted_transfer_hook_program(&program_id),
+            ErrorCode::UntrustedTransferHookProgram
+        );
+    }
+
+    for extension in mint_state.get_extension_types()? {
+        require!(
+            matches!(
+                extension,
+                ExtensionType::TransferFeeConfig
+                    | ExtensionType::InterestBearingConfig
+                    | ExtensionType::MetadataPointer
+                    | ExtensionType::TokenMetadata
+                    | ExtensionType::TransferHook
+            ),
+            ErrorCode::UnsupportedMintExtension
+        );
+    }
+    Ok(())
+}
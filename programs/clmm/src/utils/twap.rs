@@ -0,0 +1,40 @@
+use anchor_lang::prelude::*;
+use crate::cpi::accounts::Observe;
+use crate::utils::ErrorCode;
+
+/// Time-weighted averages over the last `seconds_ago` seconds of a pool.
+pub struct Twap {
+    pub arithmetic_mean_tick: i32,
+    pub harmonic_mean_liquidity: u128,
+}
+
+/// Calls `observe` on the pool for `[seconds_ago, 0]` and reduces the two
+/// observations into a time-weighted mean tick and liquidity.
+pub fn consult<'info>(
+    ctx: CpiContext<'_, '_, '_, 'info, Observe<'info>>,
+    seconds_ago: u32,
+) -> Result<Twap> {
+    require!(seconds_ago > 0, ErrorCode::InvalidTwapWindow);
+
+    let result = crate::cpi::observe(ctx, vec![seconds_ago, 0])?.get();
+
+    let tick_cumulatives_delta = result.tick_cumulatives[1]
+        .wrapping_sub(result.tick_cumulatives[0]);
+    let seconds_per_liquidity_delta = result.seconds_per_liquidity_cumulative_x64s[1]
+        .wrapping_sub(result.seconds_per_liquidity_cumulative_x64s[0]);
+
+    // Round towards negative infinity so negative averages are not biased up.
+    let mut arithmetic_mean_tick = tick_cumulatives_delta / seconds_ago as i64;
+    if tick_cumulatives_delta < 0 && tick_cumulatives_delta % seconds_ago as i64 != 0 {
+        arithmetic_mean_tick -= 1;
+    }
+
+    let harmonic_mean_liquidity = ((seconds_ago as u128) << 64)
+        .checked_div(seconds_per_liquidity_delta)
+        .unwrap_or(0);
+
+    Ok(Twap {
+        arithmetic_mean_tick: arithmetic_mean_tick as i32,
+        harmonic_mean_liquidity,
+    })
+}
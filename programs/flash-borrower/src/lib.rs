@@ -0,0 +1,75 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::{self, Mint, TokenAccount, TokenInterface, TransferChecked};
+
+declare_id!("GFEPpiFmpqHeStjMYt5mtk3q4s5ou9kJgMP77zddL99M");
+
+/// Borrower invoked by `clmm::flash` in the tests. It pays the pool back the
+/// principal encoded in `data` plus the fees the pool asks for.
+#[program]
+pub mod flash_borrower {
+    use super::*;
+
+    pub fn flash_callback(ctx: Context<FlashCallback>, fee_0: u64, fee_1: u64, data: Vec<u8>) -> Result<()> {
+        let (principal_0, principal_1) = <(u64, u64)>::try_from_slice(&data)?;
+        let accounts = &ctx.accounts;
+        repay(
+            accounts,
+            &accounts.payer_token_0,
+            &accounts.pool_token_0,
+            &accounts.token_mint_0,
+            &accounts.token_program_0,
+            principal_0 + fee_0,
+        )?;
+        repay(
+            accounts,
+            &accounts.payer_token_1,
+            &accounts.pool_token_1,
+            &accounts.token_mint_1,
+            &accounts.token_program_1,
+            principal_1 + fee_1,
+        )
+    }
+}
+
+#[derive(Accounts)]
+pub struct FlashCallback<'info> {
+    pub payer: Signer<'info>,
+    #[account(mut)]
+    pub payer_token_0: InterfaceAccount<'info, TokenAccount>,
+    #[account(mut)]
+    pub payer_token_1: InterfaceAccount<'info, TokenAccount>,
+    #[account(mut)]
+    pub pool_token_0: InterfaceAccount<'info, TokenAccount>,
+    #[account(mut)]
+    pub pool_token_1: InterfaceAccount<'info, TokenAccount>,
+    pub token_mint_0: InterfaceAccount<'info, Mint>,
+    pub token_mint_1: InterfaceAccount<'info, Mint>,
+    pub token_program_0: Interface<'info, TokenInterface>,
+    pub token_program_1: Interface<'info, TokenInterface>,
+}
+
+fn repay<'info>(
+    accounts: &FlashCallback<'info>,
+    from: &InterfaceAccount<'info, TokenAccount>,
+    to: &InterfaceAccount<'info, TokenAccount>,
+    mint: &InterfaceAccount<'info, Mint>,
+    token_program: &Interface<'info, TokenInterface>,
+    amount: u64,
+) -> Result<()> {
+    if amount == 0 {
+        return Ok(());
+    }
+    token_interface::transfer_checked(
+        CpiContext::new(
+            token_program.to_account_info(),
+            TransferChecked {
+                from: from.to_account_info(),
+                mint: mint.to_account_info(),
+                to: to.to_account_info(),
+                authority: accounts.payer.to_account_info(),
+            },
+        ),
+        amount,
+        mint.decimals,
+    )
+}
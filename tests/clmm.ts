@@ -1,8 +1,17 @@
 import * as anchor from "@coral-xyz/anchor";
 import { Program } from "@coral-xyz/anchor";
 import { Clmm } from "../target/types/clmm";
+import { FlashBorrower } from "../target/types/flash_borrower";
 import { assert } from "chai";
-import { PublicKey, SystemProgram, Keypair } from "@solana/web3.js";
+import {
+  PublicKey,
+  SystemProgram,
+  Keypair,
+  Transaction,
+  AccountMeta,
+  LAMPORTS_PER_SOL,
+  TransactionInstruction,
+} from "@solana/web3.js";
 import {
   createMint,
   TOKEN_PROGRAM_ID,
@@ -10,11 +19,22 @@ import {
   mintTo,
   getAccount,
   createAssociatedTokenAccount,
+  getOrCreateAssociatedTokenAccount,
+  getAssociatedTokenAddressSync,
+  TOKEN_2022_PROGRAM_ID,
+  ExtensionType,
+  getMintLen,
+  createInitializeMintInstruction,
+  createInitializeTransferFeeConfigInstruction,
+  createInitializeMintCloseAuthorityInstruction,
+  createInitializeTransferHookInstruction,
+  NATIVE_MINT,
 } from "@solana/spl-token";
 
 describe("clmm - pool creation and position opening test", () => {
   anchor.setProvider(anchor.AnchorProvider.env());
   const program = anchor.workspace.clmm as Program<Clmm>;
+  const borrower = anchor.workspace.flashBorrower as Program<FlashBorrower>;
 
   const TICK_SPACING = 60;
   const INITIAL_SQRT_PRICE = new anchor.BN("79228162514264337593543950336"); // sqrt(1) * 2^96
@@ -26,6 +46,8 @@ describe("clmm - pool creation and position opening test", () => {
   let poolBump: number;
   let tokenVault0Keypair: Keypair;
   let tokenVault1Keypair: Keypair;
+  let eventAuthority: PublicKey;
+  let configPda: PublicKey;
   
 
   let userTokenAccount0: PublicKey;
@@ -47,6 +69,391 @@ describe("clmm - pool creation and position opening test", () => {
     return arrayIdx * ticksPerArrayI32 * tickSpacing;
   }
 
+  const connection = program.provider.connection;
+  const wallet = program.provider.wallet as anchor.Wallet;
+
+  // Wide enough that no swap in these tests leaves the range.
+  const WIDE_LOWER = -18000;
+  const WIDE_UPPER = 18000;
+  // The pool's toy curve moves the price by exactly this many ticks per swap.
+  const SWAP_TICK_MOVE = 1000;
+  const PAUSE_SWAP = 1 << 0;
+
+  type TestMint = { mint: PublicKey; tokenProgram: PublicKey };
+
+  type PoolFixture = {
+    pool: PublicKey;
+    mint0: TestMint;
+    mint1: TestMint;
+    vault0: PublicKey;
+    vault1: PublicKey;
+    user0: PublicKey;
+    user1: PublicKey;
+  };
+
+  async function expectError(promise: Promise<unknown>, code: string) {
+    try {
+      await promise;
+    } catch (e) {
+      const logs = [...(e.logs ?? []), ...(e.simulationResponse?.logs ?? [])].join("\n");
+      assert.isTrue(
+        e.toString().includes(code) || logs.includes(code),
+        `expected ${code}, got ${e}`
+      );
+      return;
+    }
+    assert.fail(`expected ${code}`);
+  }
+
+  const sleep = (ms: number) => new Promise((resolve) => setTimeout(resolve, ms));
+
+  // Events a transaction emitted with `emit_cpi!`, decoded from the
+  // program's invocations of itself.
+  async function cpiEvents(signature: string): Promise<{ name: string; data: any }[]> {
+    await connection.confirmTransaction(signature, "confirmed");
+    const tx = await connection.getTransaction(signature, {
+      commitment: "confirmed",
+      maxSupportedTransactionVersion: 0,
+    });
+    const accountKeys = tx.transaction.message.getAccountKeys();
+    const events: { name: string; data: any }[] = [];
+    tx.meta.innerInstructions.forEach(({ instructions }) =>
+      instructions.forEach(({ programIdIndex, data }) => {
+        if (!accountKeys.get(programIdIndex).equals(program.programId)) return;
+        // The first 8 bytes tag the invocation as an event.
+        const payload = Buffer.from(anchor.utils.bytes.bs58.decode(data)).slice(8);
+        const event = program.coder.events.decode(payload.toString("base64"));
+        if (event) events.push(event);
+      })
+    );
+    return events;
+  }
+
+  async function balance(account: PublicKey, tokenProgram = TOKEN_PROGRAM_ID): Promise<number> {
+    return Number((await getAccount(connection, account, undefined, tokenProgram)).amount);
+  }
+
+  async function createUser(): Promise<Keypair> {
+    const user = Keypair.generate();
+    await program.provider.sendAndConfirm(
+      new Transaction().add(
+        SystemProgram.transfer({
+          fromPubkey: wallet.publicKey,
+          toPubkey: user.publicKey,
+          lamports: LAMPORTS_PER_SOL,
+        })
+      )
+    );
+    return user;
+  }
+
+  async function createTokenAccount(m: TestMint, owner: PublicKey): Promise<PublicKey> {
+    const account = await getOrCreateAssociatedTokenAccount(
+      connection,
+      wallet.payer,
+      m.mint,
+      owner,
+      false,
+      undefined,
+      undefined,
+      m.tokenProgram
+    );
+    return account.address;
+  }
+
+  // A mint without freeze authority and 1e9 tokens in the wallet's account.
+  async function createFundedMint(): Promise<TestMint> {
+    const mint = await createMint(connection, wallet.payer, wallet.publicKey, null, 6);
+    const m = { mint, tokenProgram: TOKEN_PROGRAM_ID };
+    await mintTo(connection, wallet.payer, mint, await createTokenAccount(m, wallet.publicKey), wallet.publicKey, 1000000000);
+    return m;
+  }
+
+  // Token-2022 mint with the given extensions, initialized by `extensionIxs`.
+  async function createMint2022(
+    extensions: ExtensionType[],
+    extensionIxs: (mint: PublicKey) => TransactionInstruction[]
+  ): Promise<TestMint> {
+    const mint = Keypair.generate();
+    const space = getMintLen(extensions);
+    await program.provider.sendAndConfirm(
+      new Transaction().add(
+        SystemProgram.createAccount({
+          fromPubkey: wallet.publicKey,
+          newAccountPubkey: mint.publicKey,
+          space,
+          lamports: await connection.getMinimumBalanceForRentExemption(space),
+          programId: TOKEN_2022_PROGRAM_ID,
+        }),
+        ...extensionIxs(mint.publicKey),
+        createInitializeMintInstruction(mint.publicKey, 6, wallet.publicKey, null, TOKEN_2022_PROGRAM_ID)
+      ),
+      [mint]
+    );
+    return { mint: mint.publicKey, tokenProgram: TOKEN_2022_PROGRAM_ID };
+  }
+
+  function sortMints(a: TestMint, b: TestMint): [TestMint, TestMint] {
+    return Buffer.compare(a.mint.toBuffer(), b.mint.toBuffer()) < 0 ? [a, b] : [b, a];
+  }
+
+  function poolPdaFor(mint0: PublicKey, mint1: PublicKey): PublicKey {
+    return PublicKey.findProgramAddressSync(
+      [Buffer.from("pool"), mint0.toBuffer(), mint1.toBuffer(), i32ToLeBytes(TICK_SPACING)],
+      program.programId
+    )[0];
+  }
+
+  function tickArrayPda(pool: PublicKey, tick: number): PublicKey {
+    return PublicKey.findProgramAddressSync(
+      [Buffer.from("tick_array"), pool.toBuffer(), i32ToLeBytes(getTickArrayStartIndex(tick, TICK_SPACING))],
+      program.programId
+    )[0];
+  }
+
+  function positionPda(owner: PublicKey, pool: PublicKey, lower: number, upper: number): PublicKey {
+    return PublicKey.findProgramAddressSync(
+      [Buffer.from("position"), owner.toBuffer(), pool.toBuffer(), i32ToLeBytes(lower), i32ToLeBytes(upper)],
+      program.programId
+    )[0];
+  }
+
+  function initializePool(
+    mint0: TestMint,
+    mint1: TestMint,
+    sqrtPrice = INITIAL_SQRT_PRICE
+  ): { pool: PublicKey; vault0: Keypair; vault1: Keypair; tx: Promise<string> } {
+    const pool = poolPdaFor(mint0.mint, mint1.mint);
+    const vault0 = Keypair.generate();
+    const vault1 = Keypair.generate();
+    const tx = program.methods
+      .initializePool(TICK_SPACING, sqrtPrice)
+      .accountsStrict({
+        payer: wallet.publicKey,
+        pool,
+        config: configPda,
+        tokenMint0: mint0.mint,
+        tokenMint1: mint1.mint,
+        tokenVault0: vault0.publicKey,
+        tokenVault1: vault1.publicKey,
+        systemProgram: SystemProgram.programId,
+        tokenProgram0: mint0.tokenProgram,
+        tokenProgram1: mint1.tokenProgram,
+        rent: anchor.web3.SYSVAR_RENT_PUBKEY,
+        eventAuthority,
+        program: program.programId,
+      })
+      .signers([vault0, vault1])
+      .rpc();
+    return { pool, vault0, vault1, tx };
+  }
+
+  // Fresh pool at tick 0 over two new funded mints, or the given ones.
+  async function createPool(a?: TestMint, b?: TestMint): Promise<PoolFixture> {
+    const [mint0, mint1] = sortMints(a ?? (await createFundedMint()), b ?? (await createFundedMint()));
+    const { pool, vault0, vault1, tx } = initializePool(mint0, mint1);
+    await tx;
+    return {
+      pool,
+      mint0,
+      mint1,
+      vault0: vault0.publicKey,
+      vault1: vault1.publicKey,
+      user0: getAssociatedTokenAddressSync(mint0.mint, wallet.publicKey, false, mint0.tokenProgram),
+      user1: getAssociatedTokenAddressSync(mint1.mint, wallet.publicKey, false, mint1.tokenProgram),
+    };
+  }
+
+  // Pool pairing wrapped SOL with a new mint; the wallet's wSOL account is
+  // created empty so SOL gets wrapped on demand.
+  async function createNativePool(): Promise<PoolFixture> {
+    const native = { mint: NATIVE_MINT, tokenProgram: TOKEN_PROGRAM_ID };
+    await createTokenAccount(native, wallet.publicKey);
+    return createPool(native, await createFundedMint());
+  }
+
+  // Accounts shared by most pool instructions.
+  function poolAccounts(f: PoolFixture) {
+    return {
+      pool: f.pool,
+      poolToken0: f.vault0,
+      poolToken1: f.vault1,
+      tokenMint0: f.mint0.mint,
+      tokenMint1: f.mint1.mint,
+      tokenProgram0: f.mint0.tokenProgram,
+      tokenProgram1: f.mint1.tokenProgram,
+      eventAuthority,
+      program: program.programId,
+    };
+  }
+
+  function openPosition(
+    f: PoolFixture,
+    lower: number,
+    upper: number,
+    liquidity: number,
+    opts: { owner?: PublicKey; lockUntil?: number; nativeSol?: boolean } = {}
+  ): Promise<string> {
+    const owner = opts.owner ?? wallet.publicKey;
+    return program.methods
+      .openPosition(
+        owner,
+        lower,
+        upper,
+        new anchor.BN(liquidity),
+        getTickArrayStartIndex(lower, TICK_SPACING),
+        getTickArrayStartIndex(upper, TICK_SPACING),
+        opts.nativeSol ?? false,
+        new anchor.BN(opts.lockUntil ?? 0)
+      )
+      .accountsStrict({
+        ...poolAccounts(f),
+        lowerTickArray: tickArrayPda(f.pool, lower),
+        upperTickArray: tickArrayPda(f.pool, upper),
+        position: positionPda(owner, f.pool, lower, upper),
+        userToken0: f.user0,
+        userToken1: f.user1,
+        payer: wallet.publicKey,
+        systemProgram: SystemProgram.programId,
+        rent: anchor.web3.SYSVAR_RENT_PUBKEY,
+      })
+      .rpc();
+  }
+
+  // Adds to the wallet's position, through the given tick arrays or the
+  // ones holding its ticks.
+  function increaseLiquidity(
+    f: PoolFixture,
+    lower: number,
+    upper: number,
+    liquidity: number,
+    opts: { tickArrays?: [PublicKey, PublicKey] } = {}
+  ): Promise<string> {
+    const [lowerTickArray, upperTickArray] = opts.tickArrays ?? [tickArrayPda(f.pool, lower), tickArrayPda(f.pool, upper)];
+    return program.methods
+      .increaseLiquidity(lower, upper, new anchor.BN(liquidity))
+      .accountsStrict({
+        ...poolAccounts(f),
+        lowerTickArray,
+        upperTickArray,
+        position: positionPda(wallet.publicKey, f.pool, lower, upper),
+        userToken0: f.user0,
+        userToken1: f.user1,
+        payer: wallet.publicKey,
+        systemProgram: SystemProgram.programId,
+        rent: anchor.web3.SYSVAR_RENT_PUBKEY,
+      })
+      .rpc();
+  }
+
+  // Withdraws from `owner`'s position, signed by `signer` (the owner or its
+  // operator), into the given recipients or the wallet's own accounts.
+  function decreaseLiquidity(
+    f: PoolFixture,
+    lower: number,
+    upper: number,
+    liquidity: number,
+    opts: {
+      signer?: Keypair;
+      owner?: PublicKey;
+      recipients?: [PublicKey, PublicKey];
+      tickArrays?: [PublicKey, PublicKey];
+    } = {}
+  ): Promise<string> {
+    const payer = opts.signer?.publicKey ?? wallet.publicKey;
+    const [recipientToken0, recipientToken1] = opts.recipients ?? [f.user0, f.user1];
+    const [lowerTickArray, upperTickArray] = opts.tickArrays ?? [tickArrayPda(f.pool, lower), tickArrayPda(f.pool, upper)];
+    const builder = program.methods
+      .decreaseLiquiduty(lower, upper, new anchor.BN(liquidity))
+      .accountsStrict({
+        ...poolAccounts(f),
+        lowerTickArray,
+        upperTickArray,
+        position: positionPda(opts.owner ?? payer, f.pool, lower, upper),
+        recipientToken0,
+        recipientToken1,
+        payer,
+        systemProgram: SystemProgram.programId,
+        rent: anchor.web3.SYSVAR_RENT_PUBKEY,
+      });
+    return (opts.signer ? builder.signers([opts.signer]) : builder).rpc();
+  }
+
+  // Closes `owner`'s position, signed by `signer` (the owner or its operator),
+  // paying out into the given recipients or the wallet's own accounts.
+  function closePosition(
+    f: PoolFixture,
+    lower: number,
+    upper: number,
+    opts: { signer?: Keypair; owner?: PublicKey; recipients?: [PublicKey, PublicKey] } = {}
+  ): Promise<string> {
+    const payer = opts.signer?.publicKey ?? wallet.publicKey;
+    const owner = opts.owner ?? payer;
+    const [recipientToken0, recipientToken1] = opts.recipients ?? [f.user0, f.user1];
+    const builder = program.methods.closePosition(lower, upper).accountsStrict({
+      ...poolAccounts(f),
+      lowerTickArray: tickArrayPda(f.pool, lower),
+      upperTickArray: tickArrayPda(f.pool, upper),
+      position: positionPda(owner, f.pool, lower, upper),
+      recipientToken0,
+      recipientToken1,
+      owner,
+      payer,
+    });
+    return (opts.signer ? builder.signers([opts.signer]) : builder).rpc();
+  }
+
+  // Tick arrays a swap from the current price passes through, including
+  // arrays that were never created.
+  async function swapTickArrays(f: PoolFixture, zeroForOne: boolean): Promise<AccountMeta[]> {
+    const { currentTick } = await program.account.pool.fetch(f.pool);
+    const after = zeroForOne ? currentTick - SWAP_TICK_MOVE : currentTick + SWAP_TICK_MOVE;
+    const [low, high] = zeroForOne ? [after, currentTick] : [currentTick, after];
+    const arrays: AccountMeta[] = [];
+    for (
+      let start = getTickArrayStartIndex(low + 1, TICK_SPACING);
+      start <= getTickArrayStartIndex(high, TICK_SPACING);
+      start += TICK_SPACING * TICKS_PER_ARRAY
+    ) {
+      arrays.push({ pubkey: tickArrayPda(f.pool, start), isSigner: false, isWritable: true });
+    }
+    return arrays;
+  }
+
+  async function swap(
+    f: PoolFixture,
+    amountIn: number,
+    zeroForOne: boolean,
+    opts: { recipient?: PublicKey; minOut?: number; nativeSol?: boolean; tickArrays?: AccountMeta[] } = {}
+  ): Promise<string> {
+    const { currentTick } = await program.account.pool.fetch(f.pool);
+    return program.methods
+      .swap(new anchor.BN(amountIn), zeroForOne, new anchor.BN(opts.minOut ?? 0), opts.nativeSol ?? false)
+      .accountsStrict({
+        ...poolAccounts(f),
+        userToken0: f.user0,
+        userToken1: f.user1,
+        recipientToken: opts.recipient ?? (zeroForOne ? f.user1 : f.user0),
+        tickArray: tickArrayPda(f.pool, currentTick),
+        payer: wallet.publicKey,
+        systemProgram: SystemProgram.programId,
+        rent: anchor.web3.SYSVAR_RENT_PUBKEY,
+      })
+      .remainingAccounts(opts.tickArrays ?? (await swapTickArrays(f, zeroForOne)))
+      .rpc();
+  }
+
+  function setPauseFlags(f: PoolFixture, flags: number, authority?: Keypair): Promise<string> {
+    const builder = program.methods.setPauseFlags(flags).accountsStrict({
+      authority: authority?.publicKey ?? wallet.publicKey,
+      config: configPda,
+      pool: f.pool,
+      eventAuthority,
+      program: program.programId,
+    });
+    return (authority ? builder.signers([authority]) : builder).rpc();
+  }
+
   before(async () => {
     console.log("Setting up test environment (creating mints and deriving PDAs)...");
 
@@ -73,6 +480,10 @@ describe("clmm - pool creation and position opening test", () => {
       6
     );
 
+    // Pools require canonical mint ordering.
+    if (Buffer.compare(tokenMint0.toBuffer(), tokenMint1.toBuffer()) > 0) {
+      [tokenMint0, tokenMint1] = [tokenMint1, tokenMint0];
+    }
 
     [poolPda, poolBump] = PublicKey.findProgramAddressSync(
       [
@@ -84,6 +495,16 @@ describe("clmm - pool creation and position opening test", () => {
       program.programId
     );
 
+    [eventAuthority] = PublicKey.findProgramAddressSync(
+      [Buffer.from("__event_authority")],
+      program.programId
+    );
+
+    [configPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("config")],
+      program.programId
+    );
+
     tokenVault0Keypair = anchor.web3.Keypair.generate();
     tokenVault1Keypair = anchor.web3.Keypair.generate();
 
@@ -125,6 +546,28 @@ describe("clmm - pool creation and position opening test", () => {
     console.log("Pool PDA:", poolPda.toString());
   });
 
+  it("Initializes the protocol config", async () => {
+    const [programData] = PublicKey.findProgramAddressSync(
+      [program.programId.toBuffer()],
+      anchor.web3.BPF_LOADER_UPGRADEABLE_PROGRAM_ID
+    );
+
+    await program.methods
+      .initializeConfig()
+      .accountsStrict({
+        authority: program.provider.wallet.publicKey,
+        config: configPda,
+        program: program.programId,
+        programData: programData,
+        systemProgram: SystemProgram.programId,
+      })
+      .rpc();
+
+    const configAccount = await program.account.config.fetch(configPda);
+    assert.equal(configAccount.authority.toString(), program.provider.wallet.publicKey.toString());
+    assert.equal(configAccount.trustedTransferHookPrograms.length, 0);
+  });
+
   it("Successfully creates a new CLMM pool", async () => {
     console.log("Attempting to initialize pool...");
     
@@ -133,13 +576,17 @@ describe("clmm - pool creation and position opening test", () => {
       .accountsStrict({
         payer: program.provider.wallet.publicKey,
         pool: poolPda,
+        config: configPda,
         tokenMint0: tokenMint0,
         tokenMint1: tokenMint1,
         tokenVault0: tokenVault0Keypair.publicKey,
         tokenVault1: tokenVault1Keypair.publicKey,
         systemProgram: anchor.web3.SystemProgram.programId,
-        tokenProgram: TOKEN_PROGRAM_ID,
+        tokenProgram0: TOKEN_PROGRAM_ID,
+        tokenProgram1: TOKEN_PROGRAM_ID,
         rent: anchor.web3.SYSVAR_RENT_PUBKEY,
+        eventAuthority: eventAuthority,
+        program: program.programId,
       })
       .signers([tokenVault0Keypair, tokenVault1Keypair])
       .rpc();
@@ -219,8 +666,9 @@ describe("clmm - pool creation and position opening test", () => {
           UPPER_TICK,                        // upper_tick
           LIQUIDITY_AMOUNT,
           lowerTickArrayStartIndex,
-          upperTickArrayStartIndex          
-  
+          upperTickArrayStartIndex,
+          false,                             // native_sol
+          new anchor.BN(0)                   // lock_until
         )
         .accountsStrict({
           pool: poolPda,
@@ -235,8 +683,11 @@ describe("clmm - pool creation and position opening test", () => {
           tokenMint0: tokenMint0,
           tokenMint1: tokenMint1,
           systemProgram: SystemProgram.programId,
-          tokenProgram: TOKEN_PROGRAM_ID,
+          tokenProgram0: TOKEN_PROGRAM_ID,
+          tokenProgram1: TOKEN_PROGRAM_ID,
           rent: anchor.web3.SYSVAR_RENT_PUBKEY,
+          eventAuthority: eventAuthority,
+          program: program.programId,
         })
         .rpc();
         console.log("Position opened! Transaction:", tx);
@@ -296,4 +747,1428 @@ describe("clmm - pool creation and position opening test", () => {
       console.log("Upper tick array fetch error:", e);
     }
   });
-});
\ No newline at end of file
+
+  it("Observes tick cumulatives from the pool oracle", async () => {
+    const result = await program.methods
+      .observe([0])
+      .accountsStrict({ pool: poolPda })
+      .view();
+
+    assert.equal(result.tickCumulatives.length, 1);
+    assert.equal(result.secondsPerLiquidityCumulativeX64s.length, 1);
+  });
+
+  describe("oracle observations", () => {
+    let f: PoolFixture;
+
+    const observe = (secondsAgos: number[]) =>
+      program.methods.observe(secondsAgos).accountsStrict({ pool: f.pool }).view();
+
+    before(async () => {
+      f = await createPool();
+      await openPosition(f, WIDE_LOWER, WIDE_UPPER, 200000);
+    });
+
+    it("interpolates tick cumulatives between the observations around a swap", async () => {
+      // The price sits at tick -1000 from the first swap to the second.
+      await swap(f, 1000, true);
+      await sleep(8000);
+      await swap(f, 1000, true);
+
+      const { observations, observationIndex } = await program.account.pool.fetch(f.pool);
+      const latest = observations[observationIndex].blockTimestamp;
+      assert.isAtLeast(latest - observations[observationIndex - 1].blockTimestamp, 7);
+
+      // Two and four seconds before the second swap, inside the interval.
+      const now = await connection.getBlockTime(await connection.getSlot());
+      const secondsAgo = now - latest + 2;
+      const result = await observe([secondsAgo, secondsAgo + 2]);
+      assert.equal(result.tickCumulatives[0].sub(result.tickCumulatives[1]).toNumber(), -2000);
+    });
+
+    it("rejects offsets older than the oldest observation", async () => {
+      await expectError(observe([3600]), "ObservationTooOld");
+    });
+  });
+
+  describe("events", () => {
+    let f: PoolFixture;
+
+    before(async () => {
+      f = await createPool();
+    });
+
+    it("emits the opened position", async () => {
+      const events = await cpiEvents(await openPosition(f, WIDE_LOWER, WIDE_UPPER, 200000));
+
+      const opened = events.find(({ name }) => name === "positionOpened").data;
+      assert.isTrue(opened.pool.equals(f.pool));
+      assert.isTrue(opened.position.equals(positionPda(wallet.publicKey, f.pool, WIDE_LOWER, WIDE_UPPER)));
+      assert.isTrue(opened.owner.equals(wallet.publicKey));
+      assert.equal(opened.tickLower, WIDE_LOWER);
+      assert.equal(opened.tickUpper, WIDE_UPPER);
+      assert.equal(opened.liquidity.toString(), "200000");
+      assert.equal(opened.amount0.toNumber(), 100000);
+      assert.equal(opened.amount1.toNumber(), 100000);
+    });
+
+    it("emits the prices, ticks and liquidity around a swap", async () => {
+      const before = await program.account.pool.fetch(f.pool);
+      const events = await cpiEvents(await swap(f, 1000, true));
+      const after = await program.account.pool.fetch(f.pool);
+
+      const swapped = events.find(({ name }) => name === "swap").data;
+      assert.isTrue(swapped.sender.equals(wallet.publicKey));
+      assert.equal(swapped.amountIn.toNumber(), 1000);
+      assert.equal(swapped.amountOut.toNumber(), 999);
+      assert.equal(swapped.sqrtPriceX96Before.toString(), before.sqrtPriceX96.toString());
+      assert.equal(swapped.sqrtPriceX96After.toString(), after.sqrtPriceX96.toString());
+      assert.equal(swapped.tickBefore, before.currentTick);
+      assert.equal(swapped.tickAfter, after.currentTick);
+      assert.equal(swapped.liquidity.toString(), after.globalLiquidity.toString());
+    });
+
+    it("emits no fill events for a swap that crosses no limit orders", async () => {
+      const events = await cpiEvents(await swap(f, 1000, true));
+      assert.deepEqual(
+        events.map(({ name }) => name),
+        ["swap"]
+      );
+    });
+  });
+
+  describe("vault and token account checks", () => {
+    let f: PoolFixture;
+
+    const swapWith = async (accounts: { poolToken0?: PublicKey; userToken0?: PublicKey }) =>
+      program.methods
+        .swap(new anchor.BN(1000), true, new anchor.BN(0), false)
+        .accountsStrict({
+          ...poolAccounts(f),
+          userToken0: f.user0,
+          userToken1: f.user1,
+          recipientToken: f.user1,
+          tickArray: tickArrayPda(f.pool, (await program.account.pool.fetch(f.pool)).currentTick),
+          payer: wallet.publicKey,
+          systemProgram: SystemProgram.programId,
+          rent: anchor.web3.SYSVAR_RENT_PUBKEY,
+          ...accounts,
+        })
+        .remainingAccounts(await swapTickArrays(f, true))
+        .rpc();
+
+    before(async () => {
+      f = await createPool();
+      await openPosition(f, WIDE_LOWER, WIDE_UPPER, 200000);
+    });
+
+    it("rejects a vault that is not the pool's", async () => {
+      await expectError(swapWith({ poolToken0: f.user0 }), "InvalidPoolVault");
+    });
+
+    it("rejects a user token account of the wrong mint", async () => {
+      await expectError(swapWith({ userToken0: f.user1 }), "InvalidTokenMint");
+    });
+
+    it("rejects a user token account the payer does not own", async () => {
+      const stranger = Keypair.generate();
+      await expectError(
+        swapWith({ userToken0: await createTokenAccount(f.mint0, stranger.publicKey) }),
+        "InvalidTokenAccountOwner"
+      );
+    });
+
+    it("moves tokens between the payer's accounts and the pool's vaults", async () => {
+      const vault0Before = await balance(f.vault0);
+      const vault1Before = await balance(f.vault1);
+      await swapWith({});
+
+      assert.equal((await balance(f.vault0)) - vault0Before, 1000);
+      assert.equal(vault1Before - (await balance(f.vault1)), 999);
+    });
+  });
+
+  describe("tick array validation", () => {
+    let f: PoolFixture;
+    let other: PoolFixture;
+
+    async function tickInfo(tick: number) {
+      const tickArray = await program.account.tickArray.fetch(tickArrayPda(f.pool, tick));
+      return tickArray.ticks[(tick - getTickArrayStartIndex(tick, TICK_SPACING)) / TICK_SPACING];
+    }
+
+    before(async () => {
+      f = await createPool();
+      other = await createPool();
+      await openPosition(f, -600, 600, 100000);
+      await openPosition(other, -600, 600, 100000);
+    });
+
+    it("updates both ticks of a range spanning two arrays", async () => {
+      await increaseLiquidity(f, -600, 600, 50000);
+
+      const lower = await tickInfo(-600);
+      const upper = await tickInfo(600);
+      assert.equal(lower.liquidityGross.toString(), "150000");
+      assert.equal(lower.liquidityNet.toString(), "150000");
+      assert.equal(upper.liquidityGross.toString(), "150000");
+      assert.equal(upper.liquidityNet.toString(), "-150000");
+    });
+
+    it("updates both ticks of a range inside one array", async () => {
+      await openPosition(f, 0, 600, 10000);
+      await increaseLiquidity(f, 0, 600, 20000);
+
+      const lower = await tickInfo(0);
+      const upper = await tickInfo(600);
+      assert.equal(lower.liquidityGross.toString(), "30000");
+      assert.equal(lower.liquidityNet.toString(), "30000");
+      assert.equal(upper.liquidityGross.toString(), "180000");
+      assert.equal(upper.liquidityNet.toString(), "-180000");
+    });
+
+    it("rejects another pool's tick array when adding liquidity", async () => {
+      await expectError(
+        increaseLiquidity(f, -600, 600, 10000, {
+          tickArrays: [tickArrayPda(other.pool, -600), tickArrayPda(f.pool, 600)],
+        }),
+        "ConstraintSeeds"
+      );
+    });
+
+    it("rejects another pool's tick array when removing liquidity", async () => {
+      await expectError(
+        decreaseLiquidity(f, -600, 600, 10000, {
+          tickArrays: [tickArrayPda(f.pool, -600), tickArrayPda(other.pool, 600)],
+        }),
+        "ConstraintSeeds"
+      );
+    });
+  });
+
+  describe("pause switch and reentrancy lock", () => {
+    let f: PoolFixture;
+
+    before(async () => {
+      f = await createPool();
+      await openPosition(f, WIDE_LOWER, WIDE_UPPER, 200000);
+    });
+
+    it("rejects pause flags set by anyone but the config authority", async () => {
+      await expectError(setPauseFlags(f, PAUSE_SWAP, Keypair.generate()), "InvalidConfigAuthority");
+    });
+
+    it("rejects unknown pause flags", async () => {
+      await expectError(setPauseFlags(f, 1 << 7), "InvalidPauseFlags");
+    });
+
+    it("blocks swaps while paused and allows them again once unpaused", async () => {
+      await setPauseFlags(f, PAUSE_SWAP);
+      await expectError(swap(f, 1000, true), "OperationPaused");
+
+      await setPauseFlags(f, 0);
+      const before = await balance(f.user1);
+      await swap(f, 1000, true);
+      assert.equal((await balance(f.user1)) - before, 999);
+
+      const poolAccount = await program.account.pool.fetch(f.pool);
+      assert.equal(poolAccount.status, 0, "the pool is unlocked after the swap");
+    });
+  });
+
+  describe("Token-2022 mints", () => {
+    it("grosses up deposits so the vault receives the full amount after transfer fees", async () => {
+      const feeMint = await createMint2022([ExtensionType.TransferFeeConfig], (mint) => [
+        createInitializeTransferFeeConfigInstruction(
+          mint,
+          wallet.publicKey,
+          wallet.publicKey,
+          100, // 1%
+          BigInt(1000000000),
+          TOKEN_2022_PROGRAM_ID
+        ),
+      ]);
+      await mintTo(
+        connection,
+        wallet.payer,
+        feeMint.mint,
+        await createTokenAccount(feeMint, wallet.publicKey),
+        wallet.publicKey,
+        1000000000,
+        [],
+        undefined,
+        TOKEN_2022_PROGRAM_ID
+      );
+      const f = await createPool(feeMint);
+      const feeVault = f.mint0.mint.equals(feeMint.mint) ? f.vault0 : f.vault1;
+      const feeUser = f.mint0.mint.equals(feeMint.mint) ? f.user0 : f.user1;
+
+      const userBefore = await balance(feeUser, TOKEN_2022_PROGRAM_ID);
+      await openPosition(f, WIDE_LOWER, WIDE_UPPER, 200000);
+
+      assert.equal(await balance(feeVault, TOKEN_2022_PROGRAM_ID), 100000);
+      assert.isAbove(userBefore - (await balance(feeUser, TOKEN_2022_PROGRAM_ID)), 100000);
+    });
+
+    it("rejects mints with unsupported extensions", async () => {
+      const closableMint = await createMint2022([ExtensionType.MintCloseAuthority], (mint) => [
+        createInitializeMintCloseAuthorityInstruction(mint, wallet.publicKey, TOKEN_2022_PROGRAM_ID),
+      ]);
+      const [mint0, mint1] = sortMints(closableMint, await createFundedMint());
+      await expectError(initializePool(mint0, mint1).tx, "UnsupportedMintExtension");
+    });
+  });
+
+  describe("transfer-hook mints", () => {
+    const hookProgram = Keypair.generate().publicKey;
+
+    function setTrustedHookPrograms(programs: PublicKey[], authority?: Keypair): Promise<string> {
+      const builder = program.methods.setTrustedTransferHookPrograms(programs).accountsStrict({
+        authority: authority?.publicKey ?? wallet.publicKey,
+        config: configPda,
+      });
+      return (authority ? builder.signers([authority]) : builder).rpc();
+    }
+
+    function createHookMint(): Promise<TestMint> {
+      return createMint2022([ExtensionType.TransferHook], (mint) => [
+        createInitializeTransferHookInstruction(mint, wallet.publicKey, hookProgram, TOKEN_2022_PROGRAM_ID),
+      ]);
+    }
+
+    after(async () => {
+      await setTrustedHookPrograms([]);
+    });
+
+    it("rejects trusted hook updates from anyone but the config authority", async () => {
+      await expectError(setTrustedHookPrograms([hookProgram], Keypair.generate()), "InvalidConfigAuthority");
+    });
+
+    it("rejects mints whose hook program is not trusted", async () => {
+      const [mint0, mint1] = sortMints(await createHookMint(), await createFundedMint());
+      await expectError(initializePool(mint0, mint1).tx, "UntrustedTransferHookProgram");
+    });
+
+    it("creates pools for mints whose hook program is trusted", async () => {
+      await setTrustedHookPrograms([hookProgram]);
+      const [mint0, mint1] = sortMints(await createHookMint(), await createFundedMint());
+      const { pool, tx } = initializePool(mint0, mint1);
+      await tx;
+
+      const poolAccount = await program.account.pool.fetch(pool);
+      assert.equal(poolAccount.tokenMint0.toString(), mint0.mint.toString());
+      assert.equal(poolAccount.tokenMint1.toString(), mint1.mint.toString());
+    });
+  });
+
+  describe("native SOL", () => {
+    let f: PoolFixture;
+    let nativeIs0: boolean;
+
+    before(async () => {
+      f = await createNativePool();
+      nativeIs0 = f.mint0.mint.equals(NATIVE_MINT);
+    });
+
+    it("wraps SOL when opening a position", async () => {
+      const lamportsBefore = await connection.getBalance(wallet.publicKey);
+      await openPosition(f, WIDE_LOWER, WIDE_UPPER, 200000, { nativeSol: true });
+
+      assert.equal(await balance(nativeIs0 ? f.vault0 : f.vault1), 100000);
+      assert.equal(await balance(nativeIs0 ? f.user0 : f.user1), 0);
+      assert.isBelow(await connection.getBalance(wallet.publicKey), lamportsBefore - 100000);
+    });
+
+    it("wraps SOL paid into a swap", async () => {
+      const otherUser = nativeIs0 ? f.user1 : f.user0;
+      const before = await balance(otherUser);
+      await swap(f, 1000, nativeIs0, { nativeSol: true });
+
+      assert.equal((await balance(otherUser)) - before, 999);
+      assert.equal(await balance(nativeIs0 ? f.user0 : f.user1), 0);
+    });
+
+    it("rejects native SOL swaps in pools without wrapped SOL", async () => {
+      const plain = await createPool();
+      await openPosition(plain, WIDE_LOWER, WIDE_UPPER, 200000);
+      await expectError(swap(plain, 1000, true, { nativeSol: true }), "NativeMintNotInPool");
+    });
+  });
+
+  describe("pool initialization checks", () => {
+    function setAllowedFreezableMints(mints: PublicKey[]): Promise<string> {
+      return program.methods
+        .setAllowedFreezableMints(mints)
+        .accountsStrict({ authority: wallet.publicKey, config: configPda })
+        .rpc();
+    }
+
+    after(async () => {
+      await setAllowedFreezableMints([]);
+    });
+
+    it("starts the pool at the tick of the initial price", async () => {
+      const [mint0, mint1] = sortMints(await createFundedMint(), await createFundedMint());
+      const sqrtPrice = INITIAL_SQRT_PRICE.add(new anchor.BN(600 * 1000000));
+      const { pool, tx } = initializePool(mint0, mint1, sqrtPrice);
+      await tx;
+
+      const poolAccount = await program.account.pool.fetch(pool);
+      assert.equal(poolAccount.currentTick, 600);
+      assert.equal(poolAccount.sqrtPriceX96.toString(), sqrtPrice.toString());
+    });
+
+    it("rejects mints passed in non-canonical order", async () => {
+      const [mint0, mint1] = sortMints(await createFundedMint(), await createFundedMint());
+      await expectError(initializePool(mint1, mint0).tx, "InvalidMintOrder");
+    });
+
+    it("rejects initial prices outside the supported range", async () => {
+      const [mint0, mint1] = sortMints(await createFundedMint(), await createFundedMint());
+      await expectError(initializePool(mint0, mint1, new anchor.BN(0)).tx, "InvalidPrice");
+    });
+
+    it("only accepts mints with a freeze authority once they are allowlisted", async () => {
+      const freezable = {
+        mint: await createMint(connection, wallet.payer, wallet.publicKey, wallet.publicKey, 6),
+        tokenProgram: TOKEN_PROGRAM_ID,
+      };
+      const [mint0, mint1] = sortMints(freezable, await createFundedMint());
+      await expectError(initializePool(mint0, mint1).tx, "MintHasFreezeAuthority");
+
+      await setAllowedFreezableMints([freezable.mint]);
+      const { pool, tx } = initializePool(mint0, mint1);
+      await tx;
+      assert.isNotNull(await program.account.pool.fetchNullable(pool));
+    });
+  });
+
+  describe("flash loans and in-range liquidity", () => {
+    let f: PoolFixture;
+
+    // The borrower repays `principal` plus the pool's fee from the wallet.
+    function flash(
+      amount0: number,
+      amount1: number,
+      principal: [number, number],
+      callbackProgram = borrower.programId
+    ): Promise<string> {
+      const data = Buffer.concat(principal.map((amount) => new anchor.BN(amount).toArrayLike(Buffer, "le", 8)));
+      return program.methods
+        .flash(new anchor.BN(amount0), new anchor.BN(amount1), data)
+        .accountsStrict({
+          ...poolAccounts(f),
+          recipientToken0: f.user0,
+          recipientToken1: f.user1,
+          callbackProgram,
+          payer: wallet.publicKey,
+        })
+        .remainingAccounts([
+          { pubkey: wallet.publicKey, isSigner: true, isWritable: false },
+          { pubkey: f.user0, isSigner: false, isWritable: true },
+          { pubkey: f.user1, isSigner: false, isWritable: true },
+          { pubkey: f.vault0, isSigner: false, isWritable: true },
+          { pubkey: f.vault1, isSigner: false, isWritable: true },
+          { pubkey: f.mint0.mint, isSigner: false, isWritable: false },
+          { pubkey: f.mint1.mint, isSigner: false, isWritable: false },
+          { pubkey: f.mint0.tokenProgram, isSigner: false, isWritable: false },
+          { pubkey: f.mint1.tokenProgram, isSigner: false, isWritable: false },
+        ])
+        .rpc();
+    }
+
+    before(async () => {
+      f = await createPool();
+      await openPosition(f, WIDE_LOWER, WIDE_UPPER, 200000);
+    });
+
+    it("lends from the vaults and credits the repaid fee to liquidity providers", async () => {
+      await flash(10000, 10000, [10000, 10000]);
+
+      assert.equal(await balance(f.vault0), 100010);
+      assert.equal(await balance(f.vault1), 100010);
+      const poolAccount = await program.account.pool.fetch(f.pool);
+      assert.isTrue(poolAccount.feeGrowthGlobal0X64.gtn(0));
+      assert.isTrue(poolAccount.feeGrowthGlobal1X64.gtn(0));
+    });
+
+    it("rejects loans that are not repaid with the fee", async () => {
+      await expectError(flash(10000, 10000, [0, 0]), "FlashLoanNotRepaid");
+    });
+
+    it("rejects the pool program itself as the callback", async () => {
+      await expectError(flash(10000, 0, [10000, 0], program.programId), "InvalidFlashCallbackProgram");
+    });
+
+    it("counts out-of-range liquidity only once the price crosses into its range", async () => {
+      await openPosition(f, 600, 1200, 50000);
+      assert.equal((await program.account.pool.fetch(f.pool)).globalLiquidity.toString(), "200000");
+
+      await swap(f, 1000, false);
+      const poolAccount = await program.account.pool.fetch(f.pool);
+      assert.equal(poolAccount.currentTick, 1000);
+      assert.equal(poolAccount.globalLiquidity.toString(), "250000");
+    });
+
+    it("rejects swaps that leave out a tick array the price passes through", async () => {
+      // Going up from tick 1000 to 2000 enters the array starting at 1800,
+      // which must be passed even though it was never created.
+      await expectError(swap(f, 1000, false, { tickArrays: [] }), "MissingTickArray");
+    });
+  });
+
+  describe("routed swaps", () => {
+    let x: TestMint;
+    let y: TestMint;
+    let z: TestMint;
+    let poolXY: PoolFixture;
+    let poolYZ: PoolFixture;
+
+    const ata = (m: TestMint) => getAssociatedTokenAddressSync(m.mint, wallet.publicKey, false, m.tokenProgram);
+
+    // Each hop sells `input` into `f`.
+    async function swapRoute(
+      hops: { f: PoolFixture; input: TestMint }[],
+      amountIn: number,
+      minOut: number,
+      output: TestMint
+    ): Promise<string> {
+      const directions = hops.map(({ f, input }) => f.mint0.mint.equals(input.mint));
+      const hopAccounts: AccountMeta[] = [];
+      const tickArrays: AccountMeta[] = [];
+      for (let i = 0; i < hops.length; i++) {
+        const { f } = hops[i];
+        [f.pool, f.vault0, f.vault1, f.mint0.mint, f.mint1.mint, f.mint0.tokenProgram, f.mint1.tokenProgram].forEach(
+          (pubkey, j) => hopAccounts.push({ pubkey, isSigner: false, isWritable: j < 3 })
+        );
+        tickArrays.push(...(await swapTickArrays(f, directions[i])));
+      }
+      return program.methods
+        .swapRoute(new anchor.BN(amountIn), directions, new anchor.BN(minOut))
+        .accountsStrict({
+          payer: wallet.publicKey,
+          userTokenIn: ata(hops[0].input),
+          userTokenOut: ata(output),
+          eventAuthority,
+          program: program.programId,
+        })
+        .remainingAccounts([...hopAccounts, ...tickArrays])
+        .rpc();
+    }
+
+    before(async () => {
+      [x, y, z] = [await createFundedMint(), await createFundedMint(), await createFundedMint()];
+      poolXY = await createPool(x, y);
+      poolYZ = await createPool(y, z);
+      await openPosition(poolXY, WIDE_LOWER, WIDE_UPPER, 200000);
+      await openPosition(poolYZ, WIDE_LOWER, WIDE_UPPER, 200000);
+    });
+
+    it("swaps through two pools and pays each pool's fee", async () => {
+      const inBefore = await balance(ata(x));
+      const outBefore = await balance(ata(z));
+      const route = [
+        { f: poolXY, input: x },
+        { f: poolYZ, input: y },
+      ];
+      await swapRoute(route, 10000, 9981, z);
+
+      assert.equal(inBefore - (await balance(ata(x))), 10000);
+      assert.equal((await balance(ata(z))) - outBefore, 9981);
+    });
+
+    it("rejects routes whose output is below the minimum", async () => {
+      const route = [
+        { f: poolXY, input: x },
+        { f: poolYZ, input: y },
+      ];
+      await expectError(swapRoute(route, 10000, 9982, z), "SlippageExceeded");
+    });
+
+    it("rejects routes through the same pool twice", async () => {
+      const route = [
+        { f: poolXY, input: x },
+        { f: poolXY, input: y },
+      ];
+      await expectError(swapRoute(route, 10000, 0, x), "InvalidRoute");
+    });
+  });
+
+  describe("swap quotes", () => {
+    let f: PoolFixture;
+
+    async function quoteSwap(amountIn: number, zeroForOne: boolean, tickArrays: AccountMeta[] = []) {
+      return program.methods
+        .quoteSwap(new anchor.BN(amountIn), zeroForOne)
+        .accountsStrict({ pool: f.pool, tokenMint0: f.mint0.mint, tokenMint1: f.mint1.mint })
+        .remainingAccounts(tickArrays)
+        .view();
+    }
+
+    before(async () => {
+      f = await createPool();
+      await openPosition(f, WIDE_LOWER, WIDE_UPPER, 200000);
+      await openPosition(f, -600, 600, 100000);
+    });
+
+    it("quotes exactly what the swap then pays out", async () => {
+      const tickArrays = await swapTickArrays(f, true);
+      // An array passed twice is counted once.
+      const quote = await quoteSwap(10000, true, [...tickArrays, ...tickArrays]);
+      assert.equal(quote.ticksCrossed, 1);
+
+      const before = await balance(f.user1);
+      await swap(f, 10000, true);
+
+      const poolAccount = await program.account.pool.fetch(f.pool);
+      assert.equal(quote.amountOut.toNumber(), (await balance(f.user1)) - before);
+      assert.equal(quote.feeAmount.toNumber(), 10);
+      assert.equal(quote.tickAfter, poolAccount.currentTick);
+      assert.equal(quote.sqrtPriceX96After.toString(), poolAccount.sqrtPriceX96.toString());
+    });
+
+    it("rejects quotes for a zero input", async () => {
+      await expectError(quoteSwap(0, true), "InsufficientInputAmount");
+    });
+
+    it("rejects quotes that leave out a tick array the swap passes through", async () => {
+      await expectError(quoteSwap(10000, true), "MissingTickArray");
+    });
+  });
+
+  describe("dynamic fees", () => {
+    let f: PoolFixture;
+
+    function setDynamicFee(enabled: boolean, min: number, max: number, authority?: Keypair): Promise<string> {
+      const builder = program.methods.setDynamicFee(enabled, min, max).accountsStrict({
+        authority: authority?.publicKey ?? wallet.publicKey,
+        config: configPda,
+        pool: f.pool,
+        eventAuthority,
+        program: program.programId,
+      });
+      return (authority ? builder.signers([authority]) : builder).rpc();
+    }
+
+    async function swapOutput(amountIn: number, zeroForOne: boolean): Promise<number> {
+      const recipient = zeroForOne ? f.user1 : f.user0;
+      const before = await balance(recipient);
+      await swap(f, amountIn, zeroForOne);
+      return (await balance(recipient)) - before;
+    }
+
+    before(async () => {
+      f = await createPool();
+      await openPosition(f, WIDE_LOWER, WIDE_UPPER, 400000);
+    });
+
+    it("rejects fee updates from anyone but the config authority", async () => {
+      await expectError(setDynamicFee(true, 1000, 5000, Keypair.generate()), "InvalidConfigAuthority");
+    });
+
+    it("rejects inverted or too high fee bounds", async () => {
+      await expectError(setDynamicFee(true, 5000, 1000), "InvalidFeeRate");
+      await expectError(setDynamicFee(true, 1000, 100001), "InvalidFeeRate");
+    });
+
+    it("charges the configured fee rate once enabled", async () => {
+      await setDynamicFee(true, 5000, 5000);
+      const poolAccount = await program.account.pool.fetch(f.pool);
+      assert.isTrue(poolAccount.dynamicFeeEnabled);
+      assert.equal(poolAccount.minFeeRate, 5000);
+      assert.equal(poolAccount.maxFeeRate, 5000);
+
+      assert.equal(await swapOutput(100000, true), 99500);
+    });
+
+    it("raises the fee with recent price movement", async () => {
+      await setDynamicFee(true, 1000, 100000);
+      const first = await swapOutput(100000, false);
+      const second = await swapOutput(100000, true);
+      assert.isBelow(second, first);
+    });
+  });
+
+  describe("limit orders", () => {
+    let f: PoolFixture;
+
+    function limitOrderPda(tick: number): PublicKey {
+      return PublicKey.findProgramAddressSync(
+        [Buffer.from("limit_order"), wallet.publicKey.toBuffer(), f.pool.toBuffer(), i32ToLeBytes(tick)],
+        program.programId
+      )[0];
+    }
+
+    function orderAccounts(tick: number) {
+      return {
+        ...poolAccounts(f),
+        tickArray: tickArrayPda(f.pool, tick),
+        limitOrder: limitOrderPda(tick),
+        userToken0: f.user0,
+        userToken1: f.user1,
+        owner: wallet.publicKey,
+      };
+    }
+
+    const placeLimitOrder = (tick: number, amount: number) =>
+      program.methods
+        .placeLimitOrder(tick, new anchor.BN(amount))
+        .accountsStrict({ ...orderAccounts(tick), systemProgram: SystemProgram.programId })
+        .rpc();
+    const claimFilledOrder = (tick: number) =>
+      program.methods.claimFilledOrder().accountsStrict(orderAccounts(tick)).rpc();
+    const cancelLimitOrder = (tick: number) =>
+      program.methods.cancelLimitOrder().accountsStrict(orderAccounts(tick)).rpc();
+
+    before(async () => {
+      f = await createPool();
+      await openPosition(f, WIDE_LOWER, WIDE_UPPER, 200000);
+    });
+
+    it("rejects orders at the current tick", async () => {
+      await expectError(placeLimitOrder(0, 10000), "InvalidTickRange");
+    });
+
+    it("rejects claims before a swap fills the order", async () => {
+      await placeLimitOrder(600, 10000);
+      await expectError(claimFilledOrder(600), "LimitOrderNotFilled");
+    });
+
+    it("sells the order to the swap crossing its tick, leaving the liquidity providers whole", async () => {
+      // The vaults hold the order's 10000 token 0 on top of the liquidity.
+      const vault0Before = await balance(f.vault0);
+      const vault1Before = await balance(f.vault1);
+
+      // 10000 of the input buys the order at tick 600's price of 1; the rest
+      // goes through the curve at the default fee.
+      const before0 = await balance(f.user0);
+      await swap(f, 20000, false);
+      assert.equal((await balance(f.user0)) - before0, 10000 + 9990);
+      const tickArray = await program.account.tickArray.fetch(tickArrayPda(f.pool, 600));
+      assert.equal(tickArray.ticks[600 / TICK_SPACING].limitOrderAmount.toNumber(), 0);
+
+      const before1 = await balance(f.user1);
+      await claimFilledOrder(600);
+      assert.equal((await balance(f.user1)) - before1, 10000);
+      assert.isNull(await program.account.limitOrder.fetchNullable(limitOrderPda(600)));
+
+      // Net of the order, the vaults only moved by the curve's 10000 in, 9990 out.
+      assert.equal(vault0Before - 10000 - (await balance(f.vault0)), 9990);
+      assert.equal((await balance(f.vault1)) - vault1Before, 10000);
+    });
+
+    it("rejects cancelling an order a swap already filled", async () => {
+      await placeLimitOrder(1800, 10000);
+      await swap(f, 20000, false);
+      await expectError(cancelLimitOrder(1800), "LimitOrderFilled");
+    });
+
+    it("leaves an order resting when the crossing swap cannot pay for it", async () => {
+      await placeLimitOrder(2400, 10000);
+      await swap(f, 1000, false);
+      assert.equal((await program.account.pool.fetch(f.pool)).currentTick, 3000);
+
+      const before = await balance(f.user0);
+      await cancelLimitOrder(2400);
+      assert.equal((await balance(f.user0)) - before, 10000);
+    });
+
+    it("refunds an open order on cancel", async () => {
+      // Below the price now, so the order sells token 1.
+      const before = await balance(f.user1);
+      await placeLimitOrder(600, 10000);
+      assert.equal(before - (await balance(f.user1)), 10000);
+
+      await cancelLimitOrder(600);
+      assert.equal(await balance(f.user1), before);
+      assert.isNull(await program.account.limitOrder.fetchNullable(limitOrderPda(600)));
+    });
+  });
+
+  describe("rebalance", () => {
+    let f: PoolFixture;
+
+    const rebalance = (oldLower: number, oldUpper: number, newLower: number, newUpper: number) =>
+      program.methods
+        .rebalancePosition(oldLower, oldUpper, newLower, newUpper, new anchor.BN(0), true, new anchor.BN(0))
+        .accountsStrict({
+          ...poolAccounts(f),
+          oldLowerTickArray: tickArrayPda(f.pool, oldLower),
+          oldUpperTickArray: tickArrayPda(f.pool, oldUpper),
+          newLowerTickArray: tickArrayPda(f.pool, newLower),
+          newUpperTickArray: tickArrayPda(f.pool, newUpper),
+          oldPosition: positionPda(wallet.publicKey, f.pool, oldLower, oldUpper),
+          newPosition: positionPda(wallet.publicKey, f.pool, newLower, newUpper),
+          userToken0: f.user0,
+          userToken1: f.user1,
+          owner: wallet.publicKey,
+          payer: wallet.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .rpc();
+
+    before(async () => {
+      f = await createPool();
+      await openPosition(f, WIDE_LOWER, WIDE_UPPER, 200000);
+    });
+
+    it("rejects rebalancing into the same range", async () => {
+      await expectError(rebalance(WIDE_LOWER, WIDE_UPPER, WIDE_LOWER, WIDE_UPPER), "InvalidPositionRange");
+    });
+
+    it("moves the liquidity to the new range and closes the old position", async () => {
+      await rebalance(WIDE_LOWER, WIDE_UPPER, -3600, 3600);
+
+      assert.isNull(
+        await program.account.position.fetchNullable(positionPda(wallet.publicKey, f.pool, WIDE_LOWER, WIDE_UPPER))
+      );
+      const position = await program.account.position.fetch(positionPda(wallet.publicKey, f.pool, -3600, 3600));
+      assert.equal(position.liquidity.toString(), "200000");
+      assert.equal((await program.account.pool.fetch(f.pool)).globalLiquidity.toString(), "200000");
+    });
+  });
+
+  describe("position locks", () => {
+    let f: PoolFixture;
+    let lockUntil: number;
+
+    const lockPosition = (until: number) =>
+      program.methods
+        .lockPosition(new anchor.BN(until))
+        .accountsStrict({
+          owner: wallet.publicKey,
+          pool: f.pool,
+          position: positionPda(wallet.publicKey, f.pool, WIDE_LOWER, WIDE_UPPER),
+          eventAuthority,
+          program: program.programId,
+        })
+        .rpc();
+
+    before(async () => {
+      f = await createPool();
+      lockUntil = (await connection.getBlockTime(await connection.getSlot())) + 3600;
+    });
+
+    it("rejects locking a position opened for someone else", async () => {
+      const other = Keypair.generate();
+      await expectError(
+        openPosition(f, WIDE_LOWER, WIDE_UPPER, 200000, { owner: other.publicKey, lockUntil }),
+        "InvalidPositionOwner"
+      );
+    });
+
+    it("rejects withdrawals from a locked position", async () => {
+      await openPosition(f, WIDE_LOWER, WIDE_UPPER, 200000, { lockUntil });
+      await expectError(decreaseLiquidity(f, WIDE_LOWER, WIDE_UPPER, 100000), "PositionLocked");
+    });
+
+    it("only extends an existing lock", async () => {
+      await expectError(lockPosition(lockUntil - 60), "InvalidLockUntil");
+
+      await lockPosition(lockUntil + 60);
+      const position = await program.account.position.fetch(
+        positionPda(wallet.publicKey, f.pool, WIDE_LOWER, WIDE_UPPER)
+      );
+      assert.equal(position.lockUntil.toNumber(), lockUntil + 60);
+    });
+
+    it("still pays out fees while locked", async () => {
+      await swap(f, 100000, true);
+
+      const before = await balance(f.user0);
+      await program.methods
+        .collectFees()
+        .accountsStrict({
+          ...poolAccounts(f),
+          position: positionPda(wallet.publicKey, f.pool, WIDE_LOWER, WIDE_UPPER),
+          lowerTickArray: tickArrayPda(f.pool, WIDE_LOWER),
+          upperTickArray: tickArrayPda(f.pool, WIDE_UPPER),
+          userToken0: f.user0,
+          userToken1: f.user1,
+          payer: wallet.publicKey,
+        })
+        .rpc();
+      assert.isAbove((await balance(f.user0)) - before, 0);
+    });
+
+    it("rejects closing a locked position until the lock expires", async () => {
+      const shortLock = (await connection.getBlockTime(await connection.getSlot())) + 5;
+      await openPosition(f, -3600, 3600, 100000, { lockUntil: shortLock });
+      await expectError(closePosition(f, -3600, 3600), "PositionLocked");
+
+      await sleep(7000);
+      const before0 = await balance(f.user0);
+      const before1 = await balance(f.user1);
+      await closePosition(f, -3600, 3600);
+      assert.isAbove((await balance(f.user0)) - before0, 0);
+      assert.isAbove((await balance(f.user1)) - before1, 0);
+      assert.isNull(await program.account.position.fetchNullable(positionPda(wallet.publicKey, f.pool, -3600, 3600)));
+    });
+  });
+
+  describe("position operators", () => {
+    let f: PoolFixture;
+    let operator: Keypair;
+
+    const setPositionOperator = (newOperator: PublicKey, owner?: Keypair) => {
+      const builder = program.methods.setPositionOperator(newOperator).accountsStrict({
+        owner: owner?.publicKey ?? wallet.publicKey,
+        position: positionPda(wallet.publicKey, f.pool, WIDE_LOWER, WIDE_UPPER),
+        eventAuthority,
+        program: program.programId,
+      });
+      return (owner ? builder.signers([owner]) : builder).rpc();
+    };
+
+    before(async () => {
+      f = await createPool();
+      operator = await createUser();
+      await openPosition(f, WIDE_LOWER, WIDE_UPPER, 200000);
+    });
+
+    it("rejects setting an operator from anyone but the owner", async () => {
+      await expectError(setPositionOperator(operator.publicKey, operator), "InvalidPositionOwner");
+    });
+
+    it("lets the operator withdraw to the owner's accounts", async () => {
+      await setPositionOperator(operator.publicKey);
+
+      const before0 = await balance(f.user0);
+      const before1 = await balance(f.user1);
+      await decreaseLiquidity(f, WIDE_LOWER, WIDE_UPPER, 100000, { signer: operator, owner: wallet.publicKey });
+      assert.isAbove((await balance(f.user0)) - before0, 0);
+      assert.isAbove((await balance(f.user1)) - before1, 0);
+    });
+
+    it("rejects withdrawals by anyone but the owner or operator", async () => {
+      const stranger = await createUser();
+      await expectError(
+        decreaseLiquidity(f, WIDE_LOWER, WIDE_UPPER, 50000, { signer: stranger, owner: wallet.publicKey }),
+        "InvalidPositionOwner"
+      );
+    });
+  });
+
+  describe("position transfers", () => {
+    let f: PoolFixture;
+
+    const transferPosition = (newOwner: PublicKey) =>
+      program.methods
+        .transferPosition()
+        .accountsStrict({
+          owner: wallet.publicKey,
+          newOwner,
+          pool: f.pool,
+          position: positionPda(wallet.publicKey, f.pool, WIDE_LOWER, WIDE_UPPER),
+          newPosition: positionPda(newOwner, f.pool, WIDE_LOWER, WIDE_UPPER),
+          systemProgram: SystemProgram.programId,
+          eventAuthority,
+          program: program.programId,
+        })
+        .rpc();
+
+    before(async () => {
+      f = await createPool();
+      await openPosition(f, WIDE_LOWER, WIDE_UPPER, 200000);
+    });
+
+    it("rejects transferring a position to its own owner", async () => {
+      await expectError(transferPosition(wallet.publicKey), "InvalidPositionOwner");
+    });
+
+    it("moves the position to the new owner's address", async () => {
+      const newOwner = Keypair.generate().publicKey;
+      await transferPosition(newOwner);
+
+      assert.isNull(
+        await program.account.position.fetchNullable(positionPda(wallet.publicKey, f.pool, WIDE_LOWER, WIDE_UPPER))
+      );
+      const position = await program.account.position.fetch(positionPda(newOwner, f.pool, WIDE_LOWER, WIDE_UPPER));
+      assert.isTrue(position.owner.equals(newOwner));
+      assert.equal(position.liquidity.toString(), "200000");
+    });
+  });
+
+  describe("splitting and merging positions", () => {
+    let f: PoolFixture;
+    let other: Keypair;
+
+    const splitPosition = (liquidity: number) =>
+      program.methods
+        .splitPosition(new anchor.BN(liquidity))
+        .accountsStrict({
+          owner: wallet.publicKey,
+          newOwner: other.publicKey,
+          pool: f.pool,
+          position: positionPda(wallet.publicKey, f.pool, WIDE_LOWER, WIDE_UPPER),
+          lowerTickArray: tickArrayPda(f.pool, WIDE_LOWER),
+          upperTickArray: tickArrayPda(f.pool, WIDE_UPPER),
+          newPosition: positionPda(other.publicKey, f.pool, WIDE_LOWER, WIDE_UPPER),
+          systemProgram: SystemProgram.programId,
+          eventAuthority,
+          program: program.programId,
+        })
+        .rpc();
+
+    // Merges `other`'s wide position into the wallet's position over the
+    // given range, co-signed by `destinationOwner`.
+    const mergeIntoWallet = (lower: number, upper: number, destinationOwner = wallet.publicKey) =>
+      program.methods
+        .mergePositions()
+        .accountsStrict({
+          owner: other.publicKey,
+          destinationOwner,
+          pool: f.pool,
+          sourcePosition: positionPda(other.publicKey, f.pool, WIDE_LOWER, WIDE_UPPER),
+          destinationPosition: positionPda(wallet.publicKey, f.pool, lower, upper),
+          lowerTickArray: tickArrayPda(f.pool, WIDE_LOWER),
+          upperTickArray: tickArrayPda(f.pool, WIDE_UPPER),
+          eventAuthority,
+          program: program.programId,
+        })
+        .signers([other])
+        .rpc();
+
+    before(async () => {
+      f = await createPool();
+      other = await createUser();
+      await openPosition(f, WIDE_LOWER, WIDE_UPPER, 200000);
+      await openPosition(f, -3600, 3600, 100000);
+    });
+
+    it("rejects splitting off more than the position holds", async () => {
+      await expectError(splitPosition(200001), "InsufficientLiquidity");
+    });
+
+    it("splits liquidity into the new owner's same-range position", async () => {
+      await splitPosition(50000);
+
+      const source = await program.account.position.fetch(positionPda(wallet.publicKey, f.pool, WIDE_LOWER, WIDE_UPPER));
+      const split = await program.account.position.fetch(positionPda(other.publicKey, f.pool, WIDE_LOWER, WIDE_UPPER));
+      assert.equal(source.liquidity.toString(), "150000");
+      assert.equal(split.liquidity.toString(), "50000");
+      assert.isTrue(split.owner.equals(other.publicKey));
+    });
+
+    it("rejects merging into a position over a different range", async () => {
+      await expectError(mergeIntoWallet(-3600, 3600), "InvalidPositionRange");
+    });
+
+    it("rejects merging into a position without its owner's signature", async () => {
+      await expectError(mergeIntoWallet(WIDE_LOWER, WIDE_UPPER, other.publicKey), "InvalidPositionOwner");
+    });
+
+    it("merges the liquidity back and closes the source", async () => {
+      await mergeIntoWallet(WIDE_LOWER, WIDE_UPPER);
+
+      const destination = await program.account.position.fetch(
+        positionPda(wallet.publicKey, f.pool, WIDE_LOWER, WIDE_UPPER)
+      );
+      assert.equal(destination.liquidity.toString(), "200000");
+      assert.isNull(
+        await program.account.position.fetchNullable(positionPda(other.publicKey, f.pool, WIDE_LOWER, WIDE_UPPER))
+      );
+    });
+  });
+
+  describe("position bundles", () => {
+    let f: PoolFixture;
+    let bundle: PublicKey;
+
+    const bundledAccounts = (lower: number, upper: number) => ({
+      ...poolAccounts(f),
+      lowerTickArray: tickArrayPda(f.pool, lower),
+      upperTickArray: tickArrayPda(f.pool, upper),
+      positionBundle: bundle,
+      userToken0: f.user0,
+      userToken1: f.user1,
+      owner: wallet.publicKey,
+    });
+
+    const openBundledPosition = (slot: number, lower: number, upper: number, liquidity: number) =>
+      program.methods
+        .openBundledPosition(slot, lower, upper, new anchor.BN(liquidity))
+        .accountsStrict({ ...bundledAccounts(lower, upper), systemProgram: SystemProgram.programId })
+        .rpc();
+
+    const closeBundledPosition = (slot: number, lower: number, upper: number) =>
+      program.methods.closeBundledPosition(slot, lower, upper).accountsStrict(bundledAccounts(lower, upper)).rpc();
+
+    const closePositionBundle = () =>
+      program.methods
+        .closePositionBundle()
+        .accountsStrict({ owner: wallet.publicKey, positionBundle: bundle })
+        .rpc();
+
+    before(async () => {
+      f = await createPool();
+      bundle = PublicKey.findProgramAddressSync(
+        [Buffer.from("position_bundle"), wallet.publicKey.toBuffer(), f.pool.toBuffer()],
+        program.programId
+      )[0];
+      await program.methods
+        .initializePositionBundle()
+        .accountsStrict({
+          owner: wallet.publicKey,
+          pool: f.pool,
+          positionBundle: bundle,
+          systemProgram: SystemProgram.programId,
+        })
+        .rpc();
+    });
+
+    it("opens positions in separate slots", async () => {
+      await openBundledPosition(0, WIDE_LOWER, WIDE_UPPER, 200000);
+      await openBundledPosition(1, -3600, 3600, 100000);
+
+      const positionBundle = await program.account.positionBundle.fetch(bundle);
+      assert.equal(positionBundle.bitmap.toNumber(), 3);
+    });
+
+    it("rejects opening a slot that is already in use", async () => {
+      await expectError(openBundledPosition(0, -3600, 3600, 100000), "InvalidBundleSlot");
+    });
+
+    it("rejects closing a bundle that still holds positions", async () => {
+      await expectError(closePositionBundle(), "PositionBundleNotEmpty");
+    });
+
+    it("closes the bundle once every slot is closed", async () => {
+      await closeBundledPosition(0, WIDE_LOWER, WIDE_UPPER);
+      await closeBundledPosition(1, -3600, 3600);
+      assert.equal((await program.account.positionBundle.fetch(bundle)).bitmap.toNumber(), 0);
+
+      await closePositionBundle();
+      assert.isNull(await program.account.positionBundle.fetchNullable(bundle));
+    });
+  });
+
+  describe("batch liquidity changes", () => {
+    let f: PoolFixture;
+
+    const batchModifyLiquidity = (entries: { lowerTick: number; upperTick: number; liquidity: number }[]) => {
+      const remainingAccounts: AccountMeta[] = [];
+      entries.forEach(({ lowerTick, upperTick }) => {
+        [
+          tickArrayPda(f.pool, lowerTick),
+          tickArrayPda(f.pool, upperTick),
+          positionPda(wallet.publicKey, f.pool, lowerTick, upperTick),
+        ].forEach((pubkey) => remainingAccounts.push({ pubkey, isSigner: false, isWritable: true }));
+      });
+      return program.methods
+        .batchModifyLiquidity(
+          entries.map(({ lowerTick, upperTick, liquidity }) => ({
+            lowerTick,
+            upperTick,
+            liquidityDelta: new anchor.BN(liquidity),
+          }))
+        )
+        .accountsStrict({
+          ...poolAccounts(f),
+          userToken0: f.user0,
+          userToken1: f.user1,
+          owner: wallet.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .remainingAccounts(remainingAccounts)
+        .rpc();
+    };
+
+    before(async () => {
+      f = await createPool();
+    });
+
+    it("rejects an empty batch", async () => {
+      await expectError(batchModifyLiquidity([]), "InvalidBatch");
+    });
+
+    it("opens several positions, including one whose address already holds lamports", async () => {
+      // Anyone can send lamports to a position address before it exists.
+      await program.provider.sendAndConfirm(
+        new Transaction().add(
+          SystemProgram.transfer({
+            fromPubkey: wallet.publicKey,
+            toPubkey: positionPda(wallet.publicKey, f.pool, -3600, 3600),
+            lamports: 1000000,
+          })
+        )
+      );
+
+      await batchModifyLiquidity([
+        { lowerTick: WIDE_LOWER, upperTick: WIDE_UPPER, liquidity: 200000 },
+        { lowerTick: -3600, upperTick: 3600, liquidity: 100000 },
+      ]);
+
+      const wide = await program.account.position.fetch(positionPda(wallet.publicKey, f.pool, WIDE_LOWER, WIDE_UPPER));
+      const narrow = await program.account.position.fetch(positionPda(wallet.publicKey, f.pool, -3600, 3600));
+      assert.equal(wide.liquidity.toString(), "200000");
+      assert.equal(narrow.liquidity.toString(), "100000");
+      assert.equal((await program.account.pool.fetch(f.pool)).globalLiquidity.toString(), "300000");
+    });
+  });
+
+  describe("zap in", () => {
+    let f: PoolFixture;
+
+    const zapIn = async (amountIn: number, minLiquidity: number) =>
+      program.methods
+        .zapIn(-3600, 3600, new anchor.BN(amountIn), true, new anchor.BN(minLiquidity))
+        .accountsStrict({
+          ...poolAccounts(f),
+          lowerTickArray: tickArrayPda(f.pool, -3600),
+          upperTickArray: tickArrayPda(f.pool, 3600),
+          position: positionPda(wallet.publicKey, f.pool, -3600, 3600),
+          userToken0: f.user0,
+          userToken1: f.user1,
+          owner: wallet.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .remainingAccounts(await swapTickArrays(f, true))
+        .rpc();
+
+    before(async () => {
+      f = await createPool();
+      await openPosition(f, WIDE_LOWER, WIDE_UPPER, 200000);
+    });
+
+    it("rejects a zap below the minimum liquidity", async () => {
+      await expectError(zapIn(10000, 1000000000), "SlippageExceeded");
+    });
+
+    it("swaps part of a single-token deposit and adds liquidity with both sides", async () => {
+      const before0 = await balance(f.user0);
+      const before1 = await balance(f.user1);
+      await zapIn(10000, 1);
+
+      const position = await program.account.position.fetch(positionPda(wallet.publicKey, f.pool, -3600, 3600));
+      assert.isAbove(position.liquidity.toNumber(), 0);
+      assert.isAtMost(before0 - (await balance(f.user0)), 10000);
+      assert.equal(await balance(f.user1), before1);
+    });
+  });
+
+  describe("zap out", () => {
+    let f: PoolFixture;
+
+    const zapOut = async (liquidity: number, amountOutMinimum: number) =>
+      program.methods
+        .zapOut(-3600, 3600, new anchor.BN(liquidity), true, new anchor.BN(amountOutMinimum))
+        .accountsStrict({
+          ...poolAccounts(f),
+          lowerTickArray: tickArrayPda(f.pool, -3600),
+          upperTickArray: tickArrayPda(f.pool, 3600),
+          position: positionPda(wallet.publicKey, f.pool, -3600, 3600),
+          userToken0: f.user0,
+          userToken1: f.user1,
+          payer: wallet.publicKey,
+        })
+        .remainingAccounts(await swapTickArrays(f, false))
+        .rpc();
+
+    before(async () => {
+      f = await createPool();
+      await openPosition(f, WIDE_LOWER, WIDE_UPPER, 200000);
+      await openPosition(f, -3600, 3600, 200000);
+    });
+
+    it("rejects a zap that pays out less than the minimum", async () => {
+      await expectError(zapOut(200000, 300000), "SlippageExceeded");
+    });
+
+    it("withdraws the position and pays it out in token 0 only", async () => {
+      const before0 = await balance(f.user0);
+      const before1 = await balance(f.user1);
+      await zapOut(200000, 199900);
+
+      // 100000 of each side; the token 1 half is swapped at the default fee.
+      assert.equal((await balance(f.user0)) - before0, 199900);
+      assert.equal(await balance(f.user1), before1);
+      const position = await program.account.position.fetch(positionPda(wallet.publicKey, f.pool, -3600, 3600));
+      assert.equal(position.liquidity.toNumber(), 0);
+    });
+  });
+
+  describe("compounding fees", () => {
+    let f: PoolFixture;
+    let keeper: Keypair;
+
+    // Compounds the wallet's position, signed by `signer` and paying any
+    // bounty to its own accounts.
+    const compoundPosition = async (signer?: Keypair) => {
+      // The rebalancing swap may go either way.
+      const tickArrays: AccountMeta[] = [];
+      [...(await swapTickArrays(f, true)), ...(await swapTickArrays(f, false))].forEach((meta) => {
+        if (!tickArrays.some(({ pubkey }) => pubkey.equals(meta.pubkey))) tickArrays.push(meta);
+      });
+      const keeperKey = signer?.publicKey ?? wallet.publicKey;
+      const builder = program.methods
+        .compoundPosition(new anchor.BN(0))
+        .accountsStrict({
+          ...poolAccounts(f),
+          position: positionPda(wallet.publicKey, f.pool, WIDE_LOWER, WIDE_UPPER),
+          lowerTickArray: tickArrayPda(f.pool, WIDE_LOWER),
+          upperTickArray: tickArrayPda(f.pool, WIDE_UPPER),
+          keeperToken0: getAssociatedTokenAddressSync(f.mint0.mint, keeperKey, false, f.mint0.tokenProgram),
+          keeperToken1: getAssociatedTokenAddressSync(f.mint1.mint, keeperKey, false, f.mint1.tokenProgram),
+          keeper: keeperKey,
+        })
+        .remainingAccounts(tickArrays);
+      return (signer ? builder.signers([signer]) : builder).rpc();
+    };
+
+    const setPositionKeeper = (newKeeper: PublicKey) =>
+      program.methods
+        .setPositionKeeper(newKeeper)
+        .accountsStrict({
+          owner: wallet.publicKey,
+          position: positionPda(wallet.publicKey, f.pool, WIDE_LOWER, WIDE_UPPER),
+          eventAuthority,
+          program: program.programId,
+        })
+        .rpc();
+
+    before(async () => {
+      f = await createPool();
+      keeper = await createUser();
+      await createTokenAccount(f.mint0, keeper.publicKey);
+      await createTokenAccount(f.mint1, keeper.publicKey);
+      await openPosition(f, WIDE_LOWER, WIDE_UPPER, 2000000);
+    });
+
+    it("rejects compounding before any fees accrue", async () => {
+      await expectError(compoundPosition(), "NoFeesToCompound");
+    });
+
+    it("reinvests accrued fees into the position", async () => {
+      await swap(f, 100000, true);
+      await swap(f, 100000, false);
+
+      await compoundPosition();
+      const position = await program.account.position.fetch(
+        positionPda(wallet.publicKey, f.pool, WIDE_LOWER, WIDE_UPPER)
+      );
+      assert.isAbove(position.liquidity.toNumber(), 2000000);
+    });
+
+    it("rejects compounding by anyone but the owner, operator or keeper", async () => {
+      await swap(f, 500000, true);
+      await swap(f, 500000, false);
+      await expectError(compoundPosition(keeper), "InvalidPositionOwner");
+    });
+
+    it("lets the keeper compound for a bounty", async () => {
+      await setPositionKeeper(keeper.publicKey);
+      const before = await program.account.position.fetch(
+        positionPda(wallet.publicKey, f.pool, WIDE_LOWER, WIDE_UPPER)
+      );
+
+      await compoundPosition(keeper);
+      const after = await program.account.position.fetch(
+        positionPda(wallet.publicKey, f.pool, WIDE_LOWER, WIDE_UPPER)
+      );
+      assert.isAbove(after.liquidity.toNumber(), before.liquidity.toNumber());
+      const ata = (m: TestMint) => getAssociatedTokenAddressSync(m.mint, keeper.publicKey, false, m.tokenProgram);
+      assert.isAbove(await balance(ata(f.mint0)), 0);
+      assert.isAbove(await balance(ata(f.mint1)), 0);
+    });
+
+    it("rejects withdrawals by the keeper", async () => {
+      await expectError(
+        decreaseLiquidity(f, WIDE_LOWER, WIDE_UPPER, 1000, { signer: keeper, owner: wallet.publicKey }),
+        "InvalidPositionOwner"
+      );
+    });
+  });
+
+  describe("third-party recipients", () => {
+    let f: PoolFixture;
+    let operator: Keypair;
+    let recipient: Keypair;
+    let recipient0: PublicKey;
+    let recipient1: PublicKey;
+
+    before(async () => {
+      f = await createPool();
+      recipient = await createUser();
+      recipient0 = await createTokenAccount(f.mint0, recipient.publicKey);
+      recipient1 = await createTokenAccount(f.mint1, recipient.publicKey);
+      await openPosition(f, WIDE_LOWER, WIDE_UPPER, 200000);
+    });
+
+    it("pays swap output to another account", async () => {
+      const walletBefore = await balance(f.user1);
+      await swap(f, 1000, true, { recipient: recipient1 });
+
+      assert.equal(await balance(recipient1), 999);
+      assert.equal(await balance(f.user1), walletBefore);
+    });
+
+    it("rejects unwrapping SOL into an account the payer does not own", async () => {
+      const native = await createNativePool();
+      const nativeIs0 = native.mint0.mint.equals(NATIVE_MINT);
+      await openPosition(native, WIDE_LOWER, WIDE_UPPER, 200000, { nativeSol: true });
+      const recipientNative = await createTokenAccount(
+        { mint: NATIVE_MINT, tokenProgram: TOKEN_PROGRAM_ID },
+        recipient.publicKey
+      );
+
+      await expectError(
+        swap(native, 1000, !nativeIs0, { recipient: recipientNative, nativeSol: true }),
+        "InvalidTokenAccountOwner"
+      );
+    });
+
+    it("lets the owner withdraw liquidity to another account", async () => {
+      const before0 = await balance(recipient0);
+      const before1 = await balance(recipient1);
+      await decreaseLiquidity(f, WIDE_LOWER, WIDE_UPPER, 50000, { recipients: [recipient0, recipient1] });
+
+      assert.isAbove((await balance(recipient0)) - before0, 0);
+      assert.isAbove((await balance(recipient1)) - before1, 0);
+    });
+
+    it("rejects an operator withdrawing to an account the owner does not own", async () => {
+      operator = await createUser();
+      await program.methods
+        .setPositionOperator(operator.publicKey)
+        .accountsStrict({
+          owner: wallet.publicKey,
+          position: positionPda(wallet.publicKey, f.pool, WIDE_LOWER, WIDE_UPPER),
+          eventAuthority,
+          program: program.programId,
+        })
+        .rpc();
+
+      await expectError(
+        decreaseLiquidity(f, WIDE_LOWER, WIDE_UPPER, 50000, {
+          signer: operator,
+          owner: wallet.publicKey,
+          recipients: [recipient0, recipient1],
+        }),
+        "InvalidTokenAccountOwner"
+      );
+    });
+
+    it("lets the owner close a position paying another account", async () => {
+      await openPosition(f, -3600, 3600, 100000);
+      const before0 = await balance(recipient0);
+      const before1 = await balance(recipient1);
+      await closePosition(f, -3600, 3600, { recipients: [recipient0, recipient1] });
+
+      assert.isAbove((await balance(recipient0)) - before0, 0);
+      assert.isAbove((await balance(recipient1)) - before1, 0);
+      assert.isNull(await program.account.position.fetchNullable(positionPda(wallet.publicKey, f.pool, -3600, 3600)));
+    });
+
+    it("rejects an operator closing a position into an account the owner does not own", async () => {
+      await expectError(
+        closePosition(f, WIDE_LOWER, WIDE_UPPER, {
+          signer: operator,
+          owner: wallet.publicKey,
+          recipients: [recipient0, recipient1],
+        }),
+        "InvalidTokenAccountOwner"
+      );
+    });
+  });
+});